@@ -0,0 +1,310 @@
+// Copyright 2016 FullContact, Inc
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Custom derives for the `lmdb_zero::traits::LmdbRaw` and
+//! `lmdb_zero::traits::LmdbOrdKey` traits.
+//!
+//! Implementing `LmdbRaw` by hand requires care: the type must have a defined
+//! layout, must not contain padding bytes (which would otherwise take part in
+//! comparisons and leak uninitialised memory into the database), and must not
+//! contain anything for which some bit patterns are invalid, such as `bool`s,
+//! enums or references. `#[derive(LmdbRaw)]` checks all of these at compile
+//! time.
+//!
+//! These derives are also available through `lmdb_zero` itself with the
+//! `derive` feature. The generated code refers to the `lmdb_zero` crate by
+//! that name, so it must be available under that name at the crate root.
+
+#![deny(missing_docs)]
+
+extern crate proc_macro;
+extern crate proc_macro2;
+#[macro_use] extern crate quote;
+extern crate syn;
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use syn::{Data, DeriveInput, Fields, Member, Type};
+use syn::spanned::Spanned;
+
+/// Derives `LmdbRaw` for a struct, checking that doing so is sound.
+///
+/// The struct must:
+///
+/// - Be `#[repr(C)]` or `#[repr(transparent)]`, optionally in addition to
+///   `#[repr(packed)]`, so that its layout is defined.
+///
+/// - Not be generic.
+///
+/// - Not contain padding bytes. This is checked by comparing the size of the
+///   struct with the sum of the sizes of its fields.
+///
+/// - Only contain fields which are themselves `LmdbRaw` or
+///   `LmdbRawIfUnaligned`. In particular, references, raw pointers, `bool`,
+///   `char`, tuples and enums are rejected.
+///
+/// The struct must also implement `Copy`, as required by `LmdbRaw` itself.
+///
+/// ## Example
+///
+/// ```
+/// extern crate lmdb_zero;
+/// #[macro_use] extern crate lmdb_zero_derive;
+///
+/// use lmdb_zero::traits::*;
+///
+/// #[repr(C)]
+/// #[derive(Clone, Copy, Debug, LmdbRaw)]
+/// struct Point {
+///   x: i32,
+///   y: i32,
+///   tags: [u8;8],
+/// }
+///
+/// # fn main() {
+/// assert_eq!("Point", <Point as LmdbRaw>::reported_type());
+/// let p = Point { x: 1, y: -2, tags: [0;8] };
+/// assert_eq!(16, p.as_lmdb_bytes().len());
+/// # }
+/// ```
+///
+/// Padding is rejected:
+///
+/// ```compile_fail
+/// # #[macro_use] extern crate lmdb_zero_derive;
+/// #[repr(C)]
+/// #[derive(Clone, Copy, LmdbRaw)]
+/// struct Padded {
+///   a: u8,
+///   b: u64,
+/// }
+/// # fn main() { }
+/// ```
+///
+/// As are types with invalid bit patterns:
+///
+/// ```compile_fail
+/// # #[macro_use] extern crate lmdb_zero_derive;
+/// #[repr(C)]
+/// #[derive(Clone, Copy, LmdbRaw)]
+/// struct Flagged {
+///   value: u8,
+///   present: bool,
+/// }
+/// # fn main() { }
+/// ```
+///
+/// And structs whose field order is up to the compiler:
+///
+/// ```compile_fail
+/// # #[macro_use] extern crate lmdb_zero_derive;
+/// #[derive(Clone, Copy, LmdbRaw)]
+/// struct Unordered {
+///   a: u32,
+///   b: u32,
+/// }
+/// # fn main() { }
+/// ```
+#[proc_macro_derive(LmdbRaw)]
+pub fn derive_lmdb_raw(input: TokenStream) -> TokenStream {
+    let input = syn::parse_macro_input!(input as DeriveInput);
+    match lmdb_raw(&input) {
+        Ok(tokens) => tokens.into(),
+        Err(err) => err.to_compile_error().into(),
+    }
+}
+
+/// Derives `LmdbOrdKey` for a struct, along with `Ord` and `PartialOrd`
+/// implementations which compare the fields in declaration order.
+///
+/// The struct must additionally implement `PartialEq` and `Eq` (derived
+/// implementations are consistent with the generated ordering) and
+/// `FromLmdbBytes`, which is normally obtained through `LmdbRaw`.
+///
+/// The generated ordering ignores padding bytes, so the type can be used
+/// with `DatabaseOptions::sort_keys_as()` and `sort_values_as()`.
+///
+/// ## Example
+///
+/// ```
+/// extern crate lmdb_zero;
+/// #[macro_use] extern crate lmdb_zero_derive;
+///
+/// #[repr(C, packed)]
+/// #[derive(Clone, Copy, PartialEq, Eq, LmdbRaw, LmdbOrdKey)]
+/// struct Version {
+///   major: u16,
+///   minor: u16,
+///   patch: u32,
+/// }
+///
+/// # fn main() {
+/// let a = Version { major: 1, minor: 300, patch: 0 };
+/// let b = Version { major: 2, minor: 0, patch: 0 };
+/// assert!(a < b);
+/// # let _ = lmdb_zero::DatabaseOptions::create_map::<Version, str>();
+/// # }
+/// ```
+#[proc_macro_derive(LmdbOrdKey)]
+pub fn derive_lmdb_ord_key(input: TokenStream) -> TokenStream {
+    let input = syn::parse_macro_input!(input as DeriveInput);
+    match lmdb_ord_key(&input) {
+        Ok(tokens) => tokens.into(),
+        Err(err) => err.to_compile_error().into(),
+    }
+}
+
+fn lmdb_raw(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let name = &input.ident;
+    let fields = struct_fields(input, "LmdbRaw")?;
+
+    if !input.generics.params.is_empty() {
+        return Err(syn::Error::new(
+            input.generics.span(),
+            "#[derive(LmdbRaw)] does not support generic structs"));
+    }
+
+    let repr = reprs(input)?;
+    if !repr.iter().any(|r| "C" == r || "transparent" == r) {
+        return Err(syn::Error::new(
+            name.span(),
+            "#[derive(LmdbRaw)] requires #[repr(C)] or #[repr(transparent)], \
+             since the field order of other structs is undefined"));
+    }
+
+    for field in fields.iter() {
+        check_raw_type(&field.ty)?;
+    }
+
+    let types: Vec<_> = fields.iter().map(|f| &f.ty).collect();
+    let padding_msg = format!(
+        "`{}` contains padding bytes; add explicit padding fields or use \
+         #[repr(C, packed)]", name);
+    let name_str = name.to_string();
+
+    Ok(quote! {
+        unsafe impl ::lmdb_zero::traits::LmdbRaw for #name {
+            fn reported_type() -> ::std::string::String {
+                ::std::borrow::ToOwned::to_owned(#name_str)
+            }
+        }
+
+        const _: () = {
+            #[allow(dead_code)]
+            fn assert_raw<T : ::lmdb_zero::traits::LmdbRawIfUnaligned>() { }
+            #[allow(dead_code)]
+            fn assert_fields_raw() {
+                #(assert_raw::<#types>();)*
+            }
+
+            assert!(::std::mem::size_of::<#name>() ==
+                    0 #(+ ::std::mem::size_of::<#types>())*,
+                    #padding_msg);
+        };
+    })
+}
+
+fn lmdb_ord_key(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let name = &input.ident;
+    let fields = struct_fields(input, "LmdbOrdKey")?;
+    let (impl_generics, ty_generics, where_clause) =
+        input.generics.split_for_impl();
+
+    // Fields of packed structs cannot be borrowed, so copy them out instead.
+    // Such structs are `LmdbRaw` in practice and thus `Copy`.
+    let packed = reprs(input)?.iter().any(|r| "packed" == r);
+    let members = fields.members();
+    let cmps = members.map(|m: Member| if packed {
+        quote! { {self.#m}.cmp(&{other.#m}) }
+    } else {
+        quote! { self.#m.cmp(&other.#m) }
+    });
+
+    Ok(quote! {
+        impl #impl_generics ::std::cmp::Ord for #name #ty_generics
+        #where_clause {
+            fn cmp(&self, other: &Self) -> ::std::cmp::Ordering {
+                #(match #cmps {
+                    ::std::cmp::Ordering::Equal => (),
+                    ordering => return ordering,
+                })*
+                ::std::cmp::Ordering::Equal
+            }
+        }
+
+        impl #impl_generics ::std::cmp::PartialOrd for #name #ty_generics
+        #where_clause {
+            fn partial_cmp(&self, other: &Self)
+                           -> ::std::option::Option<::std::cmp::Ordering> {
+                ::std::option::Option::Some(::std::cmp::Ord::cmp(self, other))
+            }
+        }
+
+        unsafe impl #impl_generics ::lmdb_zero::traits::LmdbOrdKey
+        for #name #ty_generics #where_clause { }
+    })
+}
+
+// Returns the fields of `input`, or an error if it is not a struct.
+fn struct_fields<'a>(input: &'a DeriveInput, derive: &str)
+                     -> syn::Result<&'a Fields> {
+    match input.data {
+        Data::Struct(ref data) => Ok(&data.fields),
+        Data::Enum(ref data) => Err(syn::Error::new(
+            data.enum_token.span(),
+            format!("#[derive({})] is not supported on enums, since not \
+                     every bit pattern is a valid enum value", derive))),
+        Data::Union(ref data) => Err(syn::Error::new(
+            data.union_token.span(),
+            format!("#[derive({})] is not supported on unions", derive))),
+    }
+}
+
+// Returns the names given in all `#[repr]` attributes on `input`.
+fn reprs(input: &DeriveInput) -> syn::Result<Vec<String>> {
+    let mut ret = Vec::new();
+    for attr in &input.attrs {
+        if attr.path().is_ident("repr") {
+            attr.parse_nested_meta(|meta| {
+                if let Some(ident) = meta.path.get_ident() {
+                    ret.push(ident.to_string());
+                }
+                // Skip arguments such as the `2` in `packed(2)`
+                if meta.input.peek(syn::token::Paren) {
+                    let _content;
+                    syn::parenthesized!(_content in meta.input);
+                }
+                Ok(())
+            })?;
+        }
+    }
+    Ok(ret)
+}
+
+// Rejects field types which are never valid in an `LmdbRaw` type, to give a
+// clearer error than the missing trait implementation would.
+fn check_raw_type(ty: &Type) -> syn::Result<()> {
+    let why = match *ty {
+        Type::Array(ref array) => return check_raw_type(&array.elem),
+        Type::Group(ref group) => return check_raw_type(&group.elem),
+        Type::Paren(ref paren) => return check_raw_type(&paren.elem),
+        Type::Reference(..) =>
+            "references cannot be stored in the database",
+        Type::Ptr(..) =>
+            "pointers cannot be stored in the database",
+        Type::Tuple(ref tuple) if !tuple.elems.is_empty() =>
+            "tuples have no defined layout",
+        Type::Path(ref path) if path.qself.is_none() &&
+            (path.path.is_ident("bool") || path.path.is_ident("char")) =>
+            "not every bit pattern is a valid bool or char",
+        _ => return Ok(()),
+    };
+
+    Err(syn::Error::new(ty.span(), format!(
+        "field type cannot be used in #[derive(LmdbRaw)]: {}", why)))
+}
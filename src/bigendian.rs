@@ -0,0 +1,182 @@
+// Copyright 2016 FullContact, Inc
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use std::cmp;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+
+use traits::*;
+
+/// Integer types which can be stored in a `BigEndian`.
+///
+/// This is implemented for `u16`, `i16`, `u32`, `i32`, `u64` and `i64`.
+pub trait BigEndianInt : Copy + Ord {
+    /// The byte array holding the encoded value.
+    type Bytes : LmdbRaw + Ord + Hash;
+
+    /// Encodes `self` so that the byte order of the result matches the
+    /// numeric order of the values.
+    fn to_key_bytes(self) -> Self::Bytes;
+    /// Inverts `to_key_bytes()`.
+    fn from_key_bytes(bytes: Self::Bytes) -> Self;
+}
+
+macro_rules! be_unsigned {
+    ($typ:ident, $n:expr) => {
+        impl BigEndianInt for $typ {
+            type Bytes = [u8;$n];
+
+            fn to_key_bytes(self) -> [u8;$n] {
+                self.to_be_bytes()
+            }
+
+            fn from_key_bytes(bytes: [u8;$n]) -> Self {
+                $typ::from_be_bytes(bytes)
+            }
+        }
+    }
+}
+
+// Signed values additionally have their sign bit flipped, so that negative
+// numbers sort before positive ones.
+macro_rules! be_signed {
+    ($typ:ident, $utyp:ident, $n:expr) => {
+        impl BigEndianInt for $typ {
+            type Bytes = [u8;$n];
+
+            fn to_key_bytes(self) -> [u8;$n] {
+                ((self as $utyp) ^ (1 << ($n * 8 - 1))).to_be_bytes()
+            }
+
+            fn from_key_bytes(bytes: [u8;$n]) -> Self {
+                ($utyp::from_be_bytes(bytes) ^ (1 << ($n * 8 - 1))) as $typ
+            }
+        }
+    }
+}
+
+be_unsigned!(u16, 2);
+be_signed!(i16, u16, 2);
+be_unsigned!(u32, 4);
+be_signed!(i32, u32, 4);
+be_unsigned!(u64, 8);
+be_signed!(i64, u64, 8);
+
+/// An integer stored in big-endian byte order, so that the default
+/// byte-by-byte comparison LMDB uses for keys sorts values numerically.
+///
+/// Native-endian integers only sort correctly with the `INTEGERKEY` flag or
+/// a custom comparator (see `DatabaseOptions::sort_keys_as`), and on
+/// little-endian machines produce files which cannot be read correctly
+/// elsewhere. `BigEndian` values need neither; signed types additionally
+/// have their sign bit flipped so that negative values sort first.
+///
+/// `BigEndian` has no alignment requirement, so it can always be read
+/// directly from the database.
+///
+/// ## Example
+///
+/// ```
+/// # include!("src/example_helpers.rs");
+/// # fn main() {
+/// # let env = create_env();
+/// # let db = defdb(&env);
+/// use lmdb::BigEndian;
+///
+/// let txn = lmdb::WriteTransaction::new(&env).unwrap();
+/// {
+///   let mut access = txn.access();
+///   let f = lmdb::put::Flags::empty();
+///   for &n in &[300i64, -2, 0, -70000, 5] {
+///     access.put(&db, &BigEndian::new(n), "", f).unwrap();
+///   }
+///
+///   let mut cursor = txn.cursor(&db).unwrap();
+///   let keys = lmdb::CursorIter::new(
+///     lmdb::MaybeOwned::Borrowed(&mut cursor), &*access,
+///     |c, a| c.first(a), lmdb::Cursor::next::<BigEndian<i64>,str>)
+///     .unwrap().map(|r| r.unwrap().0.get()).collect::<Vec<_>>();
+///   assert_eq!(vec![-70000, -2, 0, 5, 300], keys);
+/// }
+/// txn.commit().unwrap();
+/// # }
+/// ```
+#[derive(Clone, Copy)]
+#[repr(transparent)]
+pub struct BigEndian<T : BigEndianInt>(T::Bytes);
+
+impl<T : BigEndianInt> BigEndian<T> {
+    /// Encodes `t` in big-endian order.
+    pub fn new(t: T) -> Self {
+        BigEndian(t.to_key_bytes())
+    }
+
+    /// Decodes the contained value.
+    pub fn get(&self) -> T {
+        T::from_key_bytes(self.0)
+    }
+
+    /// Replaces the contained value.
+    pub fn set(&mut self, t: T) {
+        self.0 = t.to_key_bytes();
+    }
+}
+
+impl<T : BigEndianInt> From<T> for BigEndian<T> {
+    fn from(t: T) -> Self {
+        BigEndian::new(t)
+    }
+}
+
+unsafe impl<T : BigEndianInt> LmdbRaw for BigEndian<T> {
+    fn reported_type() -> String {
+        format!("BigEndian<{}>", <T::Bytes as LmdbRaw>::reported_type())
+    }
+}
+
+unsafe impl<T : BigEndianInt> LmdbOrdKey for BigEndian<T> {
+    fn ordered_by_bytes() -> bool { true }
+}
+
+// The encoding preserves order, so all comparisons can be done on the bytes
+// directly.
+
+impl<T : BigEndianInt> cmp::PartialEq for BigEndian<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.0 == other.0
+    }
+}
+impl<T : BigEndianInt> cmp::Eq for BigEndian<T> { }
+impl<T : BigEndianInt> cmp::PartialOrd for BigEndian<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+impl<T : BigEndianInt> cmp::Ord for BigEndian<T> {
+    fn cmp(&self, other: &Self) -> cmp::Ordering {
+        self.0.cmp(&other.0)
+    }
+}
+
+impl<T : BigEndianInt> Hash for BigEndian<T> {
+    fn hash<H : Hasher>(&self, state: &mut H) {
+        self.0.hash(state)
+    }
+}
+
+impl<T : BigEndianInt + fmt::Debug> fmt::Debug for BigEndian<T> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        self.get().fmt(fmt)
+    }
+}
+
+impl<T : BigEndianInt + fmt::Display> fmt::Display for BigEndian<T> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        self.get().fmt(fmt)
+    }
+}
@@ -0,0 +1,296 @@
+// Copyright 2016 FullContact, Inc
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use std::fmt;
+use std::marker::PhantomData;
+use std::ops::Deref;
+use std::result;
+
+use crc32c;
+use xxhash_rust::xxh64;
+
+use dbi::Database;
+use error::{Error, Result};
+use traits::*;
+use tx::{ConstAccessor, ConstTransaction};
+
+/// A checksum algorithm usable with `Checksummed`.
+pub trait Checksum {
+    /// The number of bytes the checksum occupies at the end of each value,
+    /// at most 8.
+    const LEN: usize;
+
+    /// Computes the checksum of `data`.
+    ///
+    /// The low `LEN` bytes of the result are stored, in little-endian order.
+    fn checksum(data: &[u8]) -> u64;
+}
+
+/// The CRC-32C (Castagnoli) checksum, stored as 4 little-endian bytes.
+///
+/// This is the default algorithm for `Checksummed`, and is hardware
+/// accelerated on most modern CPUs.
+#[derive(Clone, Copy, Debug)]
+pub struct Crc32c;
+
+impl Checksum for Crc32c {
+    const LEN: usize = 4;
+
+    fn checksum(data: &[u8]) -> u64 {
+        crc32c::crc32c(data) as u64
+    }
+}
+
+/// The 64-bit xxHash checksum (XXH64, seed 0), stored as 8 little-endian
+/// bytes.
+#[derive(Clone, Copy, Debug)]
+pub struct XxHash64;
+
+impl Checksum for XxHash64 {
+    const LEN: usize = 8;
+
+    fn checksum(data: &[u8]) -> u64 {
+        xxh64::xxh64(data, 0)
+    }
+}
+
+// Splits `bytes` into data and checksum, returning `None` if the checksum is
+// missing or does not match.
+fn verify<A : Checksum>(bytes: &[u8]) -> Option<&[u8]> {
+    if bytes.len() < A::LEN {
+        return None;
+    }
+
+    let (data, stored) = bytes.split_at(bytes.len() - A::LEN);
+    if stored == &A::checksum(data).to_le_bytes()[..A::LEN] {
+        Some(data)
+    } else {
+        None
+    }
+}
+
+/// A value of type `T` stored with a checksum, so that corruption of the
+/// value in the database is detected when it is read.
+///
+/// LMDB does not checksum its data pages, so corruption by the underlying
+/// storage otherwise goes unnoticed. The checksum, computed with algorithm
+/// `A`, is appended to the bytes of the value.
+///
+/// This is an unsized type, like `str`, so that it can be read from the
+/// database with `get()` and the cursor functions. Reading a `Checksummed`
+/// that way does not verify the checksum. It is verified only when the value
+/// is extracted from the `Checksummed` with its own `get()` or `data()`,
+/// which fail with `Error::ChecksumMismatch` if it does not match.
+///
+/// Values are written with `Checksummed::wrap()`, which produces a
+/// `ChecksummedBuf` that can be passed directly to `put()` and similar
+/// functions.
+///
+/// `scan_checksums()` can be used to verify a whole database at once.
+///
+/// This is only available with the `checksum` feature.
+///
+/// ## Example
+///
+/// ```
+/// # include!("src/example_helpers.rs");
+/// # fn main() {
+/// # let env = create_env();
+/// # let db = defdb(&env);
+/// use lmdb::{Checksummed, XxHash64};
+///
+/// let txn = lmdb::WriteTransaction::new(&env).unwrap();
+/// {
+///   let mut access = txn.access();
+///   let f = lmdb::put::Flags::empty();
+///   access.put(&db, "crc", &Checksummed::<str>::wrap("Berlin"), f).unwrap();
+///   access.put(&db, "xxh", &Checksummed::<str, XxHash64>::wrap("Rīga"),
+///              f).unwrap();
+///   // Simulate corruption by writing a value without a valid checksum
+///   access.put(&db, "bad", "Paris", f).unwrap();
+///
+///   assert_eq!("Berlin", access.get::<str, Checksummed<str>>(&db, "crc")
+///              .unwrap().get().unwrap());
+///   assert_eq!("Rīga", access.get::<str, Checksummed<str, XxHash64>>(
+///              &db, "xxh").unwrap().get().unwrap());
+///   // Reading the value succeeds, but extracting it does not
+///   match access.get::<str, Checksummed<str>>(&db, "bad").unwrap().get() {
+///     Err(lmdb::Error::ChecksumMismatch) => (),
+///     r => panic!("Unexpected result: {:?}", r),
+///   }
+/// }
+/// txn.commit().unwrap();
+/// # }
+/// ```
+#[repr(transparent)]
+pub struct Checksummed<T : ?Sized, A = Crc32c> {
+    _marker: PhantomData<(fn () -> T, A)>,
+    bytes: [u8],
+}
+
+impl<T : ?Sized, A : Checksum> Checksummed<T, A> {
+    /// Appends a checksum to the bytes of `value`.
+    pub fn wrap(value: &T) -> ChecksummedBuf<T, A>
+    where T : AsLmdbBytes {
+        let data = value.as_lmdb_bytes();
+        let mut bytes = Vec::with_capacity(data.len() + A::LEN);
+        bytes.extend_from_slice(data);
+        bytes.extend_from_slice(&A::checksum(data).to_le_bytes()[..A::LEN]);
+
+        ChecksummedBuf {
+            _marker: PhantomData,
+            bytes: bytes,
+        }
+    }
+
+    /// Returns whether the checksum matches the data.
+    pub fn is_valid(&self) -> bool {
+        verify::<A>(&self.bytes).is_some()
+    }
+
+    /// Verifies the checksum and returns the raw bytes of the value, without
+    /// the checksum.
+    ///
+    /// ## Errors
+    ///
+    /// Returns `Error::ChecksumMismatch` if the checksum does not match.
+    pub fn data(&self) -> Result<&[u8]> {
+        verify::<A>(&self.bytes).ok_or(Error::ChecksumMismatch)
+    }
+
+    /// Verifies the checksum and converts the value to a `T`.
+    ///
+    /// ## Errors
+    ///
+    /// Returns `Error::ChecksumMismatch` if the checksum does not match, or
+    /// `Error::ValRejected` if the data cannot be converted to a `T`.
+    pub fn get(&self) -> Result<&T>
+    where T : FromLmdbBytes {
+        T::from_lmdb_bytes(try!(self.data())).map_err(Error::ValRejected)
+    }
+
+    /// Returns the stored bytes of this value, including the checksum.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+}
+
+impl<T : ?Sized, A> fmt::Debug for Checksummed<T, A> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Checksummed").field("bytes", &&self.bytes).finish()
+    }
+}
+
+impl<T : ?Sized, A> AsLmdbBytes for Checksummed<T, A> {
+    fn as_lmdb_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+}
+
+impl<T : ?Sized, A> FromLmdbBytes for Checksummed<T, A> {
+    fn from_lmdb_bytes(bytes: &[u8]) -> result::Result<&Self, String> {
+        Ok(unsafe { &*(bytes as *const [u8] as *const Self) })
+    }
+}
+
+/// An owned value with a checksum, as produced by `Checksummed::wrap()`.
+///
+/// This dereferences to `Checksummed<T, A>`, and can be written to the
+/// database directly.
+///
+/// This is only available with the `checksum` feature.
+pub struct ChecksummedBuf<T : ?Sized, A = Crc32c> {
+    _marker: PhantomData<(fn () -> T, A)>,
+    bytes: Vec<u8>,
+}
+
+impl<T : ?Sized, A> ChecksummedBuf<T, A> {
+    /// Returns the stored bytes of this value, including the checksum.
+    pub fn into_vec(self) -> Vec<u8> {
+        self.bytes
+    }
+}
+
+impl<T : ?Sized, A> Deref for ChecksummedBuf<T, A> {
+    type Target = Checksummed<T, A>;
+
+    fn deref(&self) -> &Checksummed<T, A> {
+        unsafe { &*(&self.bytes[..] as *const [u8] as *const _) }
+    }
+}
+
+impl<T : ?Sized, A> Clone for ChecksummedBuf<T, A> {
+    fn clone(&self) -> Self {
+        ChecksummedBuf {
+            _marker: PhantomData,
+            bytes: self.bytes.clone(),
+        }
+    }
+}
+
+impl<T : ?Sized, A> fmt::Debug for ChecksummedBuf<T, A> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ChecksummedBuf").field("bytes", &self.bytes).finish()
+    }
+}
+
+impl<T : ?Sized, A> AsLmdbBytes for ChecksummedBuf<T, A> {
+    fn as_lmdb_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+}
+
+/// Verifies the checksum of every value in `db`, as written with
+/// `Checksummed<_, A>`.
+///
+/// Returns the key and value of every item whose checksum does not match,
+/// in database order. On `DUPSORT` databases, every value of every key is
+/// checked.
+///
+/// This is only available with the `checksum` feature.
+///
+/// ## Example
+///
+/// ```
+/// # include!("src/example_helpers.rs");
+/// # fn main() {
+/// # let env = create_env();
+/// # let db = defdb(&env);
+/// use lmdb::{Checksummed, Crc32c};
+///
+/// let txn = lmdb::WriteTransaction::new(&env).unwrap();
+/// {
+///   let mut access = txn.access();
+///   let f = lmdb::put::Flags::empty();
+///   access.put(&db, "a", &Checksummed::<str>::wrap("good"), f).unwrap();
+///   access.put(&db, "b", "bad", f).unwrap();
+///   access.put(&db, "c", &Checksummed::<str>::wrap("good"), f).unwrap();
+///
+///   let bad = lmdb::scan_checksums::<Crc32c>(&txn, &access, &db).unwrap();
+///   assert_eq!(vec![(b"b".to_vec(), b"bad".to_vec())], bad);
+/// }
+/// txn.commit().unwrap();
+/// # }
+/// ```
+pub fn scan_checksums<A : Checksum>(txn: &ConstTransaction,
+                                    access: &ConstAccessor,
+                                    db: &Database)
+                                    -> Result<Vec<(Vec<u8>, Vec<u8>)>> {
+    let mut cursor = try!(txn.cursor(db));
+    let mut bad = Vec::new();
+
+    let mut item = cursor.first::<[u8], [u8]>(access).to_opt();
+    while let Some((key, val)) = try!(item) {
+        if verify::<A>(val).is_none() {
+            bad.push((key.to_vec(), val.to_vec()));
+        }
+        item = cursor.next::<[u8], [u8]>(access).to_opt();
+    }
+
+    Ok(bad)
+}
@@ -0,0 +1,337 @@
+// Copyright 2016 FullContact, Inc
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use std::fmt;
+use std::marker::PhantomData;
+use std::ops::Deref;
+use std::result;
+
+use serde::{Deserialize, Serialize};
+use bincode;
+use serde_json;
+
+use error::{Error, Result};
+use traits::*;
+
+/// A serialisation format usable with `Serde`.
+///
+/// Errors are reported as strings, which `Serde` wraps in
+/// `Error::ValRejected`.
+pub trait Codec {
+    /// Encodes `value` into a new byte vector.
+    fn encode<T : Serialize + ?Sized>(value: &T)
+                                      -> result::Result<Vec<u8>, String>;
+    /// Decodes a value from `bytes`.
+    fn decode<'de, T : Deserialize<'de>>(bytes: &'de [u8])
+                                         -> result::Result<T, String>;
+}
+
+/// The compact binary format of the `bincode` crate.
+///
+/// This is the default codec for `Serde`.
+#[derive(Clone, Copy, Debug)]
+pub struct Bincode;
+
+impl Codec for Bincode {
+    fn encode<T : Serialize + ?Sized>(value: &T)
+                                      -> result::Result<Vec<u8>, String> {
+        bincode::serialize(value).map_err(|e| e.to_string())
+    }
+
+    fn decode<'de, T : Deserialize<'de>>(bytes: &'de [u8])
+                                         -> result::Result<T, String> {
+        bincode::deserialize(bytes).map_err(|e| e.to_string())
+    }
+}
+
+/// JSON, as implemented by the `serde_json` crate.
+///
+/// This is larger and slower than `Bincode`, but the stored values are
+/// readable by other tools.
+#[derive(Clone, Copy, Debug)]
+pub struct Json;
+
+impl Codec for Json {
+    fn encode<T : Serialize + ?Sized>(value: &T)
+                                      -> result::Result<Vec<u8>, String> {
+        serde_json::to_vec(value).map_err(|e| e.to_string())
+    }
+
+    fn decode<'de, T : Deserialize<'de>>(bytes: &'de [u8])
+                                         -> result::Result<T, String> {
+        serde_json::from_slice(bytes).map_err(|e| e.to_string())
+    }
+}
+
+/// The encoded form of a `T` in the format of codec `C`, as stored in the
+/// database.
+///
+/// This is an unsized type, like `str`, so that it can be read from the
+/// database with `get()` and the cursor functions without copying.
+/// Converting the bytes into a `Serde` never fails; errors are reported only
+/// when the value is decoded with `decode()`, as `Error::ValRejected`. To
+/// decode as part of the read itself, read a `Deserialized<T, C>` with
+/// `get_owned()` or the `_owned` cursor functions instead.
+///
+/// Values are written with `Serde::encode()`, which produces a `SerdeBuf`
+/// that can be passed directly to `put()` and similar functions.
+///
+/// This is only available with the `serde` feature.
+///
+/// ## Example
+///
+/// ```
+/// # include!("src/example_helpers.rs");
+/// # fn main() {
+/// # let env = create_env();
+/// # let db = defdb(&env);
+/// use std::collections::BTreeMap;
+/// use lmdb::{Serde, Bincode, Json};
+///
+/// let mut capitals = BTreeMap::new();
+/// capitals.insert("Germany".to_owned(), "Berlin".to_owned());
+/// capitals.insert("Latvia".to_owned(), "Rīga".to_owned());
+///
+/// let txn = lmdb::WriteTransaction::new(&env).unwrap();
+/// {
+///   let mut access = txn.access();
+///   let f = lmdb::put::Flags::empty();
+///   access.put(&db, "bin", &Serde::<_, Bincode>::encode(&capitals).unwrap(),
+///              f).unwrap();
+///   access.put(&db, "json", &Serde::<_, Json>::encode(&capitals).unwrap(),
+///              f).unwrap();
+///
+///   let bin = access.get::<str, Serde<BTreeMap<String, String>>>(
+///     &db, "bin").unwrap();
+///   assert_eq!(capitals, bin.decode().unwrap());
+///
+///   // JSON values are readable without going through `Serde`
+///   assert_eq!(r#"{"Germany":"Berlin","Latvia":"Rīga"}"#,
+///              access.get::<str,str>(&db, "json").unwrap());
+///   // Decoding borrows from the database where the type allows it
+///   let json = access.get::<str, Serde<BTreeMap<&str, &str>, Json>>(
+///     &db, "json").unwrap();
+///   assert_eq!("Rīga", json.decode().unwrap()["Latvia"]);
+///
+///   // Reading with the wrong type or format fails on decoding
+///   let wrong = access.get::<str, Serde<Vec<u64>, Json>>(&db, "bin")
+///     .unwrap();
+///   match wrong.decode() {
+///     Err(lmdb::Error::ValRejected(_)) => (),
+///     r => panic!("Unexpected result: {:?}", r),
+///   }
+/// }
+/// txn.commit().unwrap();
+/// # }
+/// ```
+#[repr(transparent)]
+pub struct Serde<T : ?Sized, C = Bincode> {
+    _marker: PhantomData<(fn () -> T, C)>,
+    bytes: [u8],
+}
+
+impl<T : ?Sized, C> Serde<T, C> {
+    /// Views `bytes` as an encoded value.
+    ///
+    /// This does not check that `bytes` actually decode as a `T`.
+    pub fn from_bytes(bytes: &[u8]) -> &Self {
+        unsafe { &*(bytes as *const [u8] as *const Self) }
+    }
+
+    /// Returns the encoded bytes of this value.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+}
+
+impl<T : Serialize + ?Sized, C : Codec> Serde<T, C> {
+    /// Encodes `value` with codec `C`.
+    ///
+    /// ## Errors
+    ///
+    /// Returns `Error::ValRejected` if the codec cannot represent `value`.
+    pub fn encode(value: &T) -> Result<SerdeBuf<T, C>> {
+        C::encode(value).map(SerdeBuf::from_vec).map_err(Error::ValRejected)
+    }
+}
+
+impl<T : ?Sized, C : Codec> Serde<T, C> {
+    /// Decodes this value with codec `C`.
+    ///
+    /// Where `T` supports it, the result may borrow from the database (for
+    /// example, `&str` fields in `Json`), and is then subject to the same
+    /// lifetime restrictions as any other value read from the database.
+    ///
+    /// ## Errors
+    ///
+    /// Returns `Error::ValRejected` if the bytes are not a valid encoding of
+    /// a `T`.
+    pub fn decode<'a>(&'a self) -> Result<T>
+    where T : Deserialize<'a> + Sized {
+        C::decode(&self.bytes).map_err(Error::ValRejected)
+    }
+}
+
+impl<T : ?Sized, C> fmt::Debug for Serde<T, C> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Serde").field("bytes", &&self.bytes).finish()
+    }
+}
+
+impl<T : ?Sized, C> AsLmdbBytes for Serde<T, C> {
+    fn as_lmdb_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+}
+
+impl<T : ?Sized, C> FromLmdbBytes for Serde<T, C> {
+    fn from_lmdb_bytes(bytes: &[u8]) -> result::Result<&Self, String> {
+        Ok(Serde::from_bytes(bytes))
+    }
+}
+
+/// An owned, encoded value, as produced by `Serde::encode()`.
+///
+/// This dereferences to `Serde<T, C>`, and can be written to the database
+/// directly.
+///
+/// This is only available with the `serde` feature.
+pub struct SerdeBuf<T : ?Sized, C = Bincode> {
+    _marker: PhantomData<(fn () -> T, C)>,
+    bytes: Vec<u8>,
+}
+
+impl<T : ?Sized, C> SerdeBuf<T, C> {
+    /// Takes ownership of `bytes` as an encoded value.
+    ///
+    /// This does not check that `bytes` actually decode as a `T`.
+    pub fn from_vec(bytes: Vec<u8>) -> Self {
+        SerdeBuf {
+            _marker: PhantomData,
+            bytes: bytes,
+        }
+    }
+
+    /// Returns the encoded bytes of this value.
+    pub fn into_vec(self) -> Vec<u8> {
+        self.bytes
+    }
+}
+
+impl<T : ?Sized, C> Deref for SerdeBuf<T, C> {
+    type Target = Serde<T, C>;
+
+    fn deref(&self) -> &Serde<T, C> {
+        Serde::from_bytes(&self.bytes)
+    }
+}
+
+impl<T : ?Sized, C> Clone for SerdeBuf<T, C> {
+    fn clone(&self) -> Self {
+        SerdeBuf::from_vec(self.bytes.clone())
+    }
+}
+
+impl<T : ?Sized, C> fmt::Debug for SerdeBuf<T, C> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("SerdeBuf").field("bytes", &self.bytes).finish()
+    }
+}
+
+impl<T : ?Sized, C> AsLmdbBytes for SerdeBuf<T, C> {
+    fn as_lmdb_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+}
+
+/// A `T` decoded with codec `C` as it is read from the database.
+///
+/// This is the owned counterpart to `Serde<T, C>`: reading a
+/// `Deserialized<T, C>` with `ConstAccessor::get_owned()` or the `_owned`
+/// variants of the `Cursor` functions decodes the value immediately, so that
+/// a value which cannot be decoded fails the read itself with
+/// `Error::ValRejected`. The decoded value is reached through `Deref` or
+/// taken with `into_inner()`.
+///
+/// This is only available with the `serde` feature.
+///
+/// ## Example
+///
+/// ```
+/// # include!("src/example_helpers.rs");
+/// # fn main() {
+/// # let env = create_env();
+/// # let db = defdb(&env);
+/// use lmdb::{Deserialized, Json, Serde};
+///
+/// let txn = lmdb::WriteTransaction::new(&env).unwrap();
+/// {
+///   let mut access = txn.access();
+///   let f = lmdb::put::Flags::empty();
+///   access.put(&db, "primes", &Serde::<_, Json>::encode(&[2u32, 3, 5])
+///              .unwrap(), f).unwrap();
+///
+///   let primes = access.get_owned::<str, Deserialized<Vec<u32>, Json>>(
+///     &db, "primes").unwrap().into_inner();
+///   assert_eq!(vec![2, 3, 5], primes);
+///
+///   match access.get_owned::<str, Deserialized<String, Json>>(
+///     &db, "primes")
+///   {
+///     Err(lmdb::Error::ValRejected(_)) => (),
+///     r => panic!("Unexpected result: {:?}", r),
+///   }
+/// }
+/// txn.commit().unwrap();
+/// # }
+/// ```
+pub struct Deserialized<T, C = Bincode> {
+    _marker: PhantomData<C>,
+    value: T,
+}
+
+impl<T, C> Deserialized<T, C> {
+    /// Returns the decoded value.
+    pub fn into_inner(self) -> T {
+        self.value
+    }
+}
+
+impl<T, C> Deref for Deserialized<T, C> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.value
+    }
+}
+
+impl<T : Clone, C> Clone for Deserialized<T, C> {
+    fn clone(&self) -> Self {
+        Deserialized {
+            _marker: PhantomData,
+            value: self.value.clone(),
+        }
+    }
+}
+
+impl<T : fmt::Debug, C> fmt::Debug for Deserialized<T, C> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("Deserialized").field(&self.value).finish()
+    }
+}
+
+impl<'a, T : Deserialize<'a>, C : Codec> FromLmdbBytesOwned<'a>
+for Deserialized<T, C> {
+    fn from_lmdb_bytes_owned(bytes: &'a [u8])
+                             -> result::Result<Self, String> {
+        C::decode(bytes).map(|value| Deserialized {
+            _marker: PhantomData,
+            value: value,
+        })
+    }
+}
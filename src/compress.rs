@@ -0,0 +1,393 @@
+// Copyright 2016 FullContact, Inc
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use std::borrow::Cow;
+use std::fmt;
+use std::marker::PhantomData;
+use std::ops::Deref;
+use std::result;
+
+use lz4_flex::block;
+
+use error::{Error, Result};
+use traits::*;
+
+// Codec identifiers, stored in the first byte of the header.
+const CODEC_NONE: u8 = 0;
+const CODEC_LZ4: u8 = 1;
+
+// LZ4 cannot expand its input by more than this factor, since each
+// additional byte of a length field adds at most 255 to the length.
+const LZ4_MAX_RATIO: usize = 255;
+
+/// A value of type `T` stored with transparent compression.
+///
+/// Each value starts with a small header, consisting of one byte identifying
+/// the codec, followed by the uncompressed length as an LEB128 varint. The
+/// rest of the value is the data as compressed by that codec. Values which
+/// do not get smaller when compressed are stored as-is with codec `0`, so a
+/// database can freely mix compressed and uncompressed values, and reading
+/// never needs to know which was used.
+///
+/// Currently, the only compressing codec is LZ4 (codec `1`), as implemented
+/// by the `lz4_flex` crate.
+///
+/// `T` is the type the decompressed bytes are decoded as, with
+/// `FromLmdbBytesOwned`, such as `String`, `Vec<u8>` (the default) or
+/// `Deserialized<U>`. It is not checked when writing, since the value to be
+/// compressed is usually a borrowed form of `T` such as `str`.
+///
+/// This is an unsized type, like `str`, so that it can be read from the
+/// database with `get()` and the cursor functions. The header is validated
+/// at that point; the data itself is only checked when it is decompressed
+/// with `decompress()` or `decode()`. To decompress and decode as part of the
+/// read itself, read a `Decompressed<T>` with `get_owned()` or the `_owned`
+/// cursor functions instead. Values are written with
+/// `Compressed::compress()`, which produces a `CompressedBuf` that can be
+/// passed directly to `put()` and similar functions.
+///
+/// Every value read this way must have been written with `compress()`.
+/// Values written directly, such as before compression was adopted, have no
+/// header and cannot reliably be told apart from compressed values. Most are
+/// rejected with `Error::ValRejected`, but some may be misread. Such values
+/// should be rewritten with `compress()` (for example, by reading them as
+/// `[u8]`) before being read as `Compressed`.
+///
+/// This is only available with the `compression` feature.
+///
+/// ## Example
+///
+/// ```
+/// # include!("src/example_helpers.rs");
+/// # fn main() {
+/// # let env = create_env();
+/// # let db = defdb(&env);
+/// use lmdb::{Compressed, Decompressed};
+///
+/// let big = "All work and no play makes Jack a dull boy. ".repeat(100);
+///
+/// let txn = lmdb::WriteTransaction::new(&env).unwrap();
+/// {
+///   let mut access = txn.access();
+///   let f = lmdb::put::Flags::empty();
+///   access.put(&db, "big", &Compressed::<String>::compress(&big[..]), f)
+///     .unwrap();
+///   access.put(&db, "small", &Compressed::<String>::compress("Hi"), f)
+///     .unwrap();
+///
+///   let stored = access.get::<str,Compressed<String>>(&db, "big").unwrap();
+///   assert!(stored.is_compressed());
+///   assert!(stored.as_bytes().len() < big.len());
+///   assert_eq!(big, stored.decode().unwrap());
+///
+///   // Too short to benefit, so stored uncompressed
+///   let stored = access.get::<str,Compressed<String>>(&db, "small").unwrap();
+///   assert!(!stored.is_compressed());
+///   assert_eq!(&b"Hi"[..], &*stored.decompress().unwrap());
+///
+///   // Decompressing as part of the read
+///   let small: String = access.get_owned::<str,Decompressed<String>>(
+///     &db, "small").unwrap().into_inner();
+///   assert_eq!("Hi", small);
+///
+///   // A header claiming more data than LZ4 could have produced is rejected
+///   // without attempting to decompress
+///   access.put(&db, "bad", &[1u8, 0xFF, 0xFF, 0xFF, 0xFF, 0x0F, 0][..], f)
+///     .unwrap();
+///   match access.get::<str,Compressed>(&db, "bad") {
+///     Err(lmdb::Error::ValRejected(_)) => (),
+///     r => panic!("Unexpected result: {:?}", r),
+///   }
+/// }
+/// txn.commit().unwrap();
+/// # }
+/// ```
+#[repr(transparent)]
+pub struct Compressed<T = Vec<u8>> {
+    _marker: PhantomData<fn () -> T>,
+    bytes: [u8],
+}
+
+// The parsed header of a compressed value.
+struct Header {
+    codec: u8,
+    original_len: usize,
+    data_offset: usize,
+}
+
+// Parses the header at the start of `bytes`.
+fn parse_header(bytes: &[u8]) -> result::Result<Header, String> {
+    if bytes.is_empty() {
+        return Err("Compressed value has no header".to_owned());
+    }
+
+    let codec = bytes[0];
+    if CODEC_NONE != codec && CODEC_LZ4 != codec {
+        return Err(format!("Unknown compression codec {}", codec));
+    }
+
+    let mut original_len = 0u64;
+    let mut shift = 0u32;
+    for (ix, &b) in bytes[1..].iter().enumerate() {
+        let part = (b & 0x7F) as u64;
+        if shift > 63 || (part << shift) >> shift != part {
+            return Err("Compressed value length overflows".to_owned());
+        }
+        original_len |= part << shift;
+        shift += 7;
+
+        if 0 == b & 0x80 {
+            if original_len > usize::MAX as u64 {
+                return Err("Compressed value length overflows".to_owned());
+            }
+            let original_len = original_len as usize;
+            let data_offset = ix + 2;
+            let data_len = bytes.len() - data_offset;
+            if CODEC_NONE == codec && data_len != original_len {
+                return Err(format!(
+                    "Uncompressed value has length {}, but header says {}",
+                    data_len, original_len));
+            }
+            // Checked here, since decompressing allocates the original
+            // length up front.
+            if CODEC_LZ4 == codec &&
+                original_len > data_len.saturating_mul(LZ4_MAX_RATIO)
+            {
+                return Err(format!(
+                    "LZ4 data of length {} cannot decompress to {} bytes",
+                    data_len, original_len));
+            }
+
+            return Ok(Header {
+                codec: codec,
+                original_len: original_len,
+                data_offset: data_offset,
+            });
+        }
+    }
+
+    Err("Compressed value header is truncated".to_owned())
+}
+
+// Decompresses `bytes`, which must have a valid header.
+fn decompress(bytes: &[u8], header: Header)
+              -> result::Result<Cow<'_, [u8]>, String> {
+    let data = &bytes[header.data_offset..];
+    match header.codec {
+        CODEC_LZ4 => {
+            let decompressed = try!(
+                block::decompress(data, header.original_len)
+                    .map_err(|e| format!("Corrupt LZ4 data: {}", e)));
+            if decompressed.len() != header.original_len {
+                return Err(format!(
+                    "LZ4 data decompressed to {} bytes, but header says {}",
+                    decompressed.len(), header.original_len));
+            }
+            Ok(Cow::Owned(decompressed))
+        },
+        _ => Ok(Cow::Borrowed(data)),
+    }
+}
+
+impl<T> Compressed<T> {
+    /// Compresses the bytes of `value`.
+    ///
+    /// If compression does not make the value any smaller, it is stored
+    /// uncompressed instead.
+    pub fn compress<V : AsLmdbBytes + ?Sized>(value: &V)
+                                              -> CompressedBuf<T> {
+        let raw = value.as_lmdb_bytes();
+        let compressed = block::compress(raw);
+
+        let (codec, data) = if compressed.len() < raw.len() {
+            (CODEC_LZ4, &compressed[..])
+        } else {
+            (CODEC_NONE, raw)
+        };
+
+        let mut bytes = Vec::with_capacity(data.len() + 11);
+        bytes.push(codec);
+        let mut len = raw.len();
+        loop {
+            if len < 0x80 {
+                bytes.push(len as u8);
+                break;
+            }
+            bytes.push((len as u8) | 0x80);
+            len >>= 7;
+        }
+        bytes.extend_from_slice(data);
+
+        CompressedBuf {
+            _marker: PhantomData,
+            bytes: bytes,
+        }
+    }
+
+    /// Returns the stored bytes of this value, including the header.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    fn header(&self) -> Header {
+        // Already validated by `from_lmdb_bytes()` or `compress()`
+        parse_header(&self.bytes).expect("Compressed header became invalid")
+    }
+
+    /// Returns whether the data is actually compressed.
+    pub fn is_compressed(&self) -> bool {
+        CODEC_NONE != self.header().codec
+    }
+
+    /// Returns the length of the data when decompressed.
+    pub fn original_len(&self) -> usize {
+        self.header().original_len
+    }
+
+    /// Decompresses this value, returning the original bytes.
+    ///
+    /// If the value is not compressed, this borrows the data directly.
+    ///
+    /// ## Errors
+    ///
+    /// Returns `Error::ValRejected` if the compressed data is corrupt.
+    pub fn decompress(&self) -> Result<Cow<'_, [u8]>> {
+        decompress(&self.bytes, self.header()).map_err(Error::ValRejected)
+    }
+
+    /// Decompresses this value and decodes it as a `T`.
+    ///
+    /// ## Errors
+    ///
+    /// Returns `Error::ValRejected` if the compressed data is corrupt or
+    /// cannot be decoded as a `T`.
+    pub fn decode(&self) -> Result<T>
+    where T : for<'b> FromLmdbBytesOwned<'b> {
+        let data = try!(self.decompress());
+        T::from_lmdb_bytes_owned(&data).map_err(Error::ValRejected)
+    }
+}
+
+impl<T> fmt::Debug for Compressed<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Compressed").field("bytes", &&self.bytes).finish()
+    }
+}
+
+impl<T> AsLmdbBytes for Compressed<T> {
+    fn as_lmdb_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+}
+
+impl<T> FromLmdbBytes for Compressed<T> {
+    fn from_lmdb_bytes(bytes: &[u8]) -> result::Result<&Self, String> {
+        try!(parse_header(bytes));
+        Ok(unsafe { &*(bytes as *const [u8] as *const Self) })
+    }
+}
+
+/// An owned compressed value, as produced by `Compressed::compress()`.
+///
+/// This dereferences to `Compressed`, and can be written to the database
+/// directly.
+///
+/// This is only available with the `compression` feature.
+pub struct CompressedBuf<T = Vec<u8>> {
+    _marker: PhantomData<fn () -> T>,
+    bytes: Vec<u8>,
+}
+
+impl<T> CompressedBuf<T> {
+    /// Returns the stored bytes of this value, including the header.
+    pub fn into_vec(self) -> Vec<u8> {
+        self.bytes
+    }
+}
+
+impl<T> Deref for CompressedBuf<T> {
+    type Target = Compressed<T>;
+
+    fn deref(&self) -> &Compressed<T> {
+        unsafe { &*(&self.bytes[..] as *const [u8] as *const Compressed<T>) }
+    }
+}
+
+impl<T> Clone for CompressedBuf<T> {
+    fn clone(&self) -> Self {
+        CompressedBuf {
+            _marker: PhantomData,
+            bytes: self.bytes.clone(),
+        }
+    }
+}
+
+impl<T> fmt::Debug for CompressedBuf<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("CompressedBuf").field("bytes", &self.bytes).finish()
+    }
+}
+
+impl<T> AsLmdbBytes for CompressedBuf<T> {
+    fn as_lmdb_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+}
+
+/// A `T` decompressed and decoded as it is read from the database.
+///
+/// This is the owned counterpart to `Compressed<T>`: reading a
+/// `Decompressed<T>` with `ConstAccessor::get_owned()` or the `_owned`
+/// variants of the `Cursor` functions decompresses and decodes the value
+/// immediately, so that corrupt data fails the read itself with
+/// `Error::ValRejected`. The decoded value is reached through `Deref` or
+/// taken with `into_inner()`.
+///
+/// This is only available with the `compression` feature.
+pub struct Decompressed<T> {
+    value: T,
+}
+
+impl<T> Decompressed<T> {
+    /// Returns the decoded value.
+    pub fn into_inner(self) -> T {
+        self.value
+    }
+}
+
+impl<T> Deref for Decompressed<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.value
+    }
+}
+
+impl<T : Clone> Clone for Decompressed<T> {
+    fn clone(&self) -> Self {
+        Decompressed { value: self.value.clone() }
+    }
+}
+
+impl<T : fmt::Debug> fmt::Debug for Decompressed<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("Decompressed").field(&self.value).finish()
+    }
+}
+
+impl<'a, T> FromLmdbBytesOwned<'a> for Decompressed<T>
+where T : for<'b> FromLmdbBytesOwned<'b> {
+    fn from_lmdb_bytes_owned(bytes: &'a [u8])
+                             -> result::Result<Self, String> {
+        let header = try!(parse_header(bytes));
+        let data = try!(decompress(bytes, header));
+        T::from_lmdb_bytes_owned(&data).map(|value| Decompressed {
+            value: value,
+        })
+    }
+}
@@ -6,19 +6,22 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
 
+use std::cmp::Ordering;
 use std::marker::PhantomData;
 use std::mem;
 use std::ptr;
 use libc::{self, c_void};
 
 use ffi;
+use ffi2;
 
-use env::Environment;
-use error::Result;
+use dbi::db;
+use env::{self, Environment, EnvRef};
+use error::{Error, Result};
 use mdb_vals::*;
 use traits::*;
 use tx::{put, del, ConstAccessor, ConstTransaction, WriteAccessor};
-use tx::assert_sensible_cursor;
+use tx::{assert_sensible_cursor, txn_env};
 
 #[derive(Debug)]
 struct CursorHandle(*mut ffi::MDB_cursor);
@@ -103,6 +106,55 @@ pub fn txn_ref<'txn,'db>(cursor: &Cursor<'txn,'db>)
                          -> &'txn ConstTransaction<'txn> {
     cursor.txn
 }
+// Opens another cursor into the same transaction and database
+pub fn reopen<'txn,'db>(cursor: &Cursor<'txn,'db>)
+                        -> Result<Cursor<'txn,'db>> {
+    let mut raw: *mut ffi::MDB_cursor = ptr::null_mut();
+    unsafe {
+        lmdb_call!(ffi::mdb_cursor_open(
+            ffi::mdb_cursor_txn(cursor.cursor.0),
+            ffi2::mdb_cursor_dbi(cursor.cursor.0), &mut raw));
+        Ok(create_cursor(raw, cursor.txn))
+    }
+}
+// Compares two keys according to the ordering of the cursor's database
+pub fn cmp_keys(cursor: &Cursor, a: &[u8], b: &[u8]) -> Ordering {
+    let mut mv_a = as_val(a);
+    let mut mv_b = as_val(b);
+    unsafe {
+        ffi::mdb_cmp(ffi::mdb_cursor_txn(cursor.cursor.0),
+                     ffi2::mdb_cursor_dbi(cursor.cursor.0),
+                     &mut mv_a, &mut mv_b).cmp(&0)
+    }
+}
+// Returns the flags of the cursor's database
+pub fn db_flags(cursor: &Cursor) -> db::Flags {
+    let mut flags: libc::c_uint = 0;
+    unsafe {
+        ffi::mdb_dbi_flags(ffi::mdb_cursor_txn(cursor.cursor.0),
+                           ffi2::mdb_cursor_dbi(cursor.cursor.0),
+                           &mut flags);
+    }
+    db::Flags::from_bits_truncate(flags)
+}
+// Compares two key/value pairs according to the ordering of the cursor's
+// database. Values are only compared on DUPSORT databases, since LMDB has no
+// data comparison function otherwise.
+pub fn cmp_items(cursor: &Cursor, a: (&[u8], &[u8]), b: (&[u8], &[u8]))
+                 -> Ordering {
+    match cmp_keys(cursor, a.0, b.0) {
+        Ordering::Equal if db_flags(cursor).contains(db::DUPSORT) => {
+            let mut mv_a = as_val(a.1);
+            let mut mv_b = as_val(b.1);
+            unsafe {
+                ffi::mdb_dcmp(ffi::mdb_cursor_txn(cursor.cursor.0),
+                              ffi2::mdb_cursor_dbi(cursor.cursor.0),
+                              &mut mv_a, &mut mv_b).cmp(&0)
+            }
+        },
+        ordering => ordering,
+    }
+}
 
 /// A read-only cursor which has been dissociated from its original
 /// transaction, so that it can be rebound later.
@@ -111,12 +163,12 @@ pub fn txn_ref<'txn,'db>(cursor: &Cursor<'txn,'db>)
 #[derive(Debug)]
 pub struct StaleCursor<'db> {
     cursor: CursorHandle,
-    env: &'db Environment,
+    env: EnvRef<'db>,
     _db: PhantomData<&'db ()>,
 }
 
 // Internal
-pub fn to_stale<'a,'db>(cursor: Cursor<'a,'db>, env: &'db Environment)
+pub fn to_stale<'a,'db>(cursor: Cursor<'a,'db>, env: EnvRef<'db>)
                         -> StaleCursor<'db> {
     StaleCursor {
         cursor: cursor.cursor,
@@ -135,7 +187,7 @@ pub fn from_stale<'txn,'db>(stale: StaleCursor<'db>,
 }
 pub fn env_ref<'a,'db>(cursor: &'a StaleCursor<'db>)
                        -> &'a Environment {
-    cursor.env
+    &cursor.env
 }
 pub fn stale_cursor_ptr<'db>(cursor: &StaleCursor<'db>)
                              -> *mut ffi::MDB_cursor {
@@ -153,6 +205,20 @@ macro_rules! cursor_get_0_kv {
         {
             self.get_0_kv(access, $op)
         }
+    };
+
+    ($(#[$doc:meta])* fn $method:ident / $owned:ident, $op:path) => {
+        cursor_get_0_kv! { $(#[$doc])* fn $method, $op }
+
+        #[doc = concat!("Like `", stringify!($method), "()`, but decodes ",
+                        "the key and value with `FromLmdbBytesOwned`.")]
+        #[inline]
+        pub fn $owned<'access, K : FromLmdbBytesOwned<'access>,
+                      V : FromLmdbBytesOwned<'access>>
+            (&mut self, access: &'access ConstAccessor) -> Result<(K, V)>
+        {
+            self.get_0_kv_owned(access, $op)
+        }
     }
 }
 
@@ -166,44 +232,131 @@ macro_rules! cursor_get_0_v {
         {
             self.get_0_v(access, $op)
         }
+    };
+
+    ($(#[$doc:meta])* fn $method:ident / $owned:ident, $op:path) => {
+        cursor_get_0_v! { $(#[$doc])* fn $method, $op }
+
+        #[doc = concat!("Like `", stringify!($method), "()`, but decodes ",
+                        "the value with `FromLmdbBytesOwned`.")]
+        #[inline]
+        pub fn $owned<'access, V : FromLmdbBytesOwned<'access>>
+            (&mut self, access: &'access ConstAccessor) -> Result<V>
+        {
+            self.get_0_v_owned(access, $op)
+        }
     }
 }
 
 impl<'txn,'db> Cursor<'txn,'db> {
+    // Attaches context to `error` from `operation` on this cursor, including
+    // `key` if the operation was given one.
+    fn context(&self, error: Error, operation: &'static str,
+               key: Option<&[u8]>) -> Error {
+        let dbi = unsafe { ffi2::mdb_cursor_dbi(self.cursor.0) };
+        env::dbi_error_context(txn_env(self.txn), error, operation, dbi, key)
+    }
+
+    // Like `context()`, for an operation given `key`.
+    fn key_context<K : AsLmdbBytes + ?Sized>(&self, error: Error,
+                                             operation: &'static str,
+                                             key: &K) -> Error {
+        self.context(error, operation, Some(key.as_lmdb_bytes()))
+    }
+
+    // The raw implementations of the `mdb_cursor_get` wrappers. Each
+    // returns the `MDB_val`s produced by LMDB, leaving the borrowed and owned
+    // variants below to decode them.
+
     #[inline]
-    fn get_0_kv<'access, K : FromLmdbBytes + ?Sized,
-                V : FromLmdbBytes + ?Sized>
-        (&mut self, access: &'access ConstAccessor,
-         op: ffi::MDB_cursor_op) -> Result<(&'access K, &'access V)>
-    {
+    fn get_0_raw(&mut self, access: &ConstAccessor, op: ffi::MDB_cursor_op)
+                 -> Result<(ffi::MDB_val, ffi::MDB_val)> {
         try!(assert_sensible_cursor(access, self));
 
         let mut out_key = EMPTY_VAL;
         let mut out_val = EMPTY_VAL;
         unsafe {
             lmdb_call!(ffi::mdb_cursor_get(
-                self.cursor.0, &mut out_key, &mut out_val, op));
+                self.cursor.0, &mut out_key, &mut out_val, op),
+                |err| self.context(err, "mdb_cursor_get", None));
         }
 
-        Ok((try!(from_val(access, &out_key)),
-            try!(from_val(access, &out_val))))
+        Ok((out_key, out_val))
     }
 
     #[inline]
-    fn get_0_v<'access, V : FromLmdbBytes + ?Sized>
-        (&mut self, access: &'access ConstAccessor,
-         op: ffi::MDB_cursor_op) -> Result<&'access V>
+    fn get_k_raw<K : AsLmdbBytes + ?Sized>
+        (&mut self, access: &ConstAccessor, key: &K, op: ffi::MDB_cursor_op)
+         -> Result<(ffi::MDB_val, ffi::MDB_val)>
     {
         try!(assert_sensible_cursor(access, self));
 
-        let mut null_key = EMPTY_VAL;
+        let mut inout_key = as_val(key);
         let mut out_val = EMPTY_VAL;
         unsafe {
             lmdb_call!(ffi::mdb_cursor_get(
-                self.cursor.0, &mut null_key, &mut out_val, op));
+                self.cursor.0, &mut inout_key, &mut out_val, op),
+                |err| self.key_context(err, "mdb_cursor_get", key));
+        }
+
+        Ok((inout_key, out_val))
+    }
+
+    #[inline]
+    fn get_kv_raw<K : AsLmdbBytes + ?Sized, V : AsLmdbBytes + ?Sized>
+        (&mut self, access: &ConstAccessor, key: &K, val: &V,
+         op: ffi::MDB_cursor_op) -> Result<ffi::MDB_val>
+    {
+        try!(assert_sensible_cursor(access, self));
+
+        let mut mv_key = as_val(key);
+        let mut inout_val = as_val(val);
+        unsafe {
+            lmdb_call!(ffi::mdb_cursor_get(
+                self.cursor.0, &mut mv_key, &mut inout_val, op),
+                |err| self.key_context(err, "mdb_cursor_get", key));
         }
 
-        from_val(access, &out_val)
+        Ok(inout_val)
+    }
+
+    #[inline]
+    fn get_0_kv<'access, K : FromLmdbBytes + ?Sized,
+                V : FromLmdbBytes + ?Sized>
+        (&mut self, access: &'access ConstAccessor,
+         op: ffi::MDB_cursor_op) -> Result<(&'access K, &'access V)>
+    {
+        let (key, val) = try!(self.get_0_raw(access, op));
+        Ok((try!(from_val(access, &key)), try!(from_val(access, &val))))
+    }
+
+    #[inline]
+    fn get_0_v<'access, V : FromLmdbBytes + ?Sized>
+        (&mut self, access: &'access ConstAccessor,
+         op: ffi::MDB_cursor_op) -> Result<&'access V>
+    {
+        let (_, val) = try!(self.get_0_raw(access, op));
+        from_val(access, &val)
+    }
+
+    #[inline]
+    fn get_0_kv_owned<'access, K : FromLmdbBytesOwned<'access>,
+                      V : FromLmdbBytesOwned<'access>>
+        (&mut self, access: &'access ConstAccessor,
+         op: ffi::MDB_cursor_op) -> Result<(K, V)>
+    {
+        let (key, val) = try!(self.get_0_raw(access, op));
+        Ok((try!(from_val_owned(access, &key)),
+            try!(from_val_owned(access, &val))))
+    }
+
+    #[inline]
+    fn get_0_v_owned<'access, V : FromLmdbBytesOwned<'access>>
+        (&mut self, access: &'access ConstAccessor,
+         op: ffi::MDB_cursor_op) -> Result<V>
+    {
+        let (_, val) = try!(self.get_0_raw(access, op));
+        from_val_owned(access, &val)
     }
 
     #[inline]
@@ -214,7 +367,8 @@ impl<'txn,'db> Cursor<'txn,'db> {
         let mut mv_val = as_val(val);
         unsafe {
             lmdb_call!(ffi::mdb_cursor_get(
-                self.cursor.0, &mut mv_key, &mut mv_val, op));
+                self.cursor.0, &mut mv_key, &mut mv_val, op),
+                |err| self.key_context(err, "mdb_cursor_get", key));
         }
 
         Ok(())
@@ -226,17 +380,8 @@ impl<'txn,'db> Cursor<'txn,'db> {
         (&mut self, access: &'access ConstAccessor,
          key: &K, val: &V, op: ffi::MDB_cursor_op) -> Result<&'access V>
     {
-        try!(assert_sensible_cursor(access, self));
-
-        let mut mv_key = as_val(key);
-        let mut inout_val = as_val(val);
-
-        unsafe {
-            lmdb_call!(ffi::mdb_cursor_get(
-                self.cursor.0, &mut mv_key, &mut inout_val, op));
-        }
-
-        from_val(access, &inout_val)
+        let val = try!(self.get_kv_raw(access, key, val, op));
+        from_val(access, &val)
     }
 
     #[inline]
@@ -245,17 +390,8 @@ impl<'txn,'db> Cursor<'txn,'db> {
         (&mut self, access: &'access ConstAccessor,
          key: &K, op: ffi::MDB_cursor_op) -> Result<&'access V>
     {
-        try!(assert_sensible_cursor(access, self));
-
-        let mut mv_key = as_val(key);
-        let mut out_val = EMPTY_VAL;
-
-        unsafe {
-            lmdb_call!(ffi::mdb_cursor_get(
-                self.cursor.0, &mut mv_key, &mut out_val, op));
-        }
-
-        from_val(access, &out_val)
+        let (_, val) = try!(self.get_k_raw(access, key, op));
+        from_val(access, &val)
     }
 
     #[inline]
@@ -264,18 +400,8 @@ impl<'txn,'db> Cursor<'txn,'db> {
         (&mut self, access: &'access ConstAccessor,
          key: &K, op: ffi::MDB_cursor_op) -> Result<(&'access K, &'access V)>
     {
-        try!(assert_sensible_cursor(access, self));
-
-        let mut inout_key = as_val(key);
-        let mut out_val = EMPTY_VAL;
-
-        unsafe {
-            lmdb_call!(ffi::mdb_cursor_get(
-                self.cursor.0, &mut inout_key, &mut out_val, op));
-        }
-
-        Ok((try!(from_val(access, &inout_key)),
-            try!(from_val(access, &out_val))))
+        let (key, val) = try!(self.get_k_raw(access, key, op));
+        Ok((try!(from_val(access, &key)), try!(from_val(access, &val))))
     }
 
     cursor_get_0_kv! {
@@ -306,7 +432,7 @@ impl<'txn,'db> Cursor<'txn,'db> {
         /// txn.commit().unwrap();
         /// # }
         /// ```
-        fn first, ffi::MDB_cursor_op::MDB_FIRST
+        fn first / first_owned, ffi::MDB_cursor_op::MDB_FIRST
     }
 
     cursor_get_0_v! {
@@ -341,7 +467,7 @@ impl<'txn,'db> Cursor<'txn,'db> {
         /// txn.commit().unwrap();
         /// # }
         /// ```
-        fn first_dup, ffi::MDB_cursor_op::MDB_FIRST_DUP
+        fn first_dup / first_dup_owned, ffi::MDB_cursor_op::MDB_FIRST_DUP
     }
 
     /// Positions the cursor at the given (key,value) pair.
@@ -434,6 +560,20 @@ impl<'txn,'db> Cursor<'txn,'db> {
         self.get_kv_v(access, key, val, ffi::MDB_cursor_op::MDB_GET_BOTH_RANGE)
     }
 
+    /// Like `seek_k_nearest_v()`, but decodes the value with
+    /// `FromLmdbBytesOwned`.
+    #[inline]
+    pub fn seek_k_nearest_v_owned<'access, K : AsLmdbBytes + ?Sized,
+                                  Q : AsLmdbBytes + ?Sized,
+                                  V : FromLmdbBytesOwned<'access>>
+        (&mut self, access: &'access ConstAccessor, key: &K, val: &Q)
+         -> Result<V>
+    {
+        let val = try!(self.get_kv_raw(
+            access, key, val, ffi::MDB_cursor_op::MDB_GET_BOTH_RANGE));
+        from_val_owned(access, &val)
+    }
+
     cursor_get_0_kv! {
         /// Returns the current key/value pair under this cursor.
         ///
@@ -462,7 +602,7 @@ impl<'txn,'db> Cursor<'txn,'db> {
         /// txn.commit().unwrap();
         /// # }
         /// ```
-        fn get_current, ffi::MDB_cursor_op::MDB_GET_CURRENT
+        fn get_current / get_current_owned, ffi::MDB_cursor_op::MDB_GET_CURRENT
     }
 
     cursor_get_0_v! {
@@ -482,7 +622,8 @@ impl<'txn,'db> Cursor<'txn,'db> {
         /// `MDB_GET_MULTIPLE` operation.
         ///
         /// See `lmdb_zero::db::DUPFIXED` for examples of usage.
-        fn get_multiple, ffi::MDB_cursor_op::MDB_GET_MULTIPLE
+        fn get_multiple / get_multiple_owned,
+            ffi::MDB_cursor_op::MDB_GET_MULTIPLE
     }
 
     cursor_get_0_v! {
@@ -493,7 +634,8 @@ impl<'txn,'db> Cursor<'txn,'db> {
         /// `MDB_NEXT_MULTIPLE` operation.
         ///
         /// See `lmdb_zero::db::DUPFIXED` for examples of usage.
-        fn next_multiple, ffi::MDB_cursor_op::MDB_NEXT_MULTIPLE
+        fn next_multiple / next_multiple_owned,
+            ffi::MDB_cursor_op::MDB_NEXT_MULTIPLE
     }
 
     cursor_get_0_kv! {
@@ -524,7 +666,7 @@ impl<'txn,'db> Cursor<'txn,'db> {
         /// txn.commit().unwrap();
         /// # }
         /// ```
-        fn last, ffi::MDB_cursor_op::MDB_LAST
+        fn last / last_owned, ffi::MDB_cursor_op::MDB_LAST
     }
 
     cursor_get_0_v! {
@@ -560,7 +702,7 @@ impl<'txn,'db> Cursor<'txn,'db> {
         /// txn.commit().unwrap();
         /// # }
         /// ```
-        fn last_dup, ffi::MDB_cursor_op::MDB_LAST_DUP
+        fn last_dup / last_dup_owned, ffi::MDB_cursor_op::MDB_LAST_DUP
     }
 
     cursor_get_0_kv! {
@@ -598,7 +740,7 @@ impl<'txn,'db> Cursor<'txn,'db> {
         /// txn.commit().unwrap();
         /// # }
         /// ```
-        fn next, ffi::MDB_cursor_op::MDB_NEXT
+        fn next / next_owned, ffi::MDB_cursor_op::MDB_NEXT
     }
 
     cursor_get_0_kv! {
@@ -634,7 +776,7 @@ impl<'txn,'db> Cursor<'txn,'db> {
         /// txn.commit().unwrap();
         /// # }
         /// ```
-        fn next_dup, ffi::MDB_cursor_op::MDB_NEXT_DUP
+        fn next_dup / next_dup_owned, ffi::MDB_cursor_op::MDB_NEXT_DUP
     }
 
     cursor_get_0_kv! {
@@ -672,7 +814,7 @@ impl<'txn,'db> Cursor<'txn,'db> {
         /// txn.commit().unwrap();
         /// # }
         /// ```
-        fn next_nodup, ffi::MDB_cursor_op::MDB_NEXT_NODUP
+        fn next_nodup / next_nodup_owned, ffi::MDB_cursor_op::MDB_NEXT_NODUP
     }
 
     cursor_get_0_kv! {
@@ -710,7 +852,7 @@ impl<'txn,'db> Cursor<'txn,'db> {
         /// txn.commit().unwrap();
         /// # }
         /// ```
-        fn prev, ffi::MDB_cursor_op::MDB_PREV
+        fn prev / prev_owned, ffi::MDB_cursor_op::MDB_PREV
     }
 
     cursor_get_0_kv! {
@@ -747,7 +889,7 @@ impl<'txn,'db> Cursor<'txn,'db> {
         /// txn.commit().unwrap();
         /// # }
         /// ```
-        fn prev_dup, ffi::MDB_cursor_op::MDB_PREV_DUP
+        fn prev_dup / prev_dup_owned, ffi::MDB_cursor_op::MDB_PREV_DUP
     }
 
     cursor_get_0_kv! {
@@ -784,7 +926,7 @@ impl<'txn,'db> Cursor<'txn,'db> {
         /// txn.commit().unwrap();
         /// # }
         /// ```
-        fn prev_nodup, ffi::MDB_cursor_op::MDB_PREV_NODUP
+        fn prev_nodup / prev_nodup_owned, ffi::MDB_cursor_op::MDB_PREV_NODUP
     }
 
     /// Positions the cursor at the first item of the given key.
@@ -825,6 +967,44 @@ impl<'txn,'db> Cursor<'txn,'db> {
         self.get_k_v(access, key, ffi::MDB_cursor_op::MDB_SET)
     }
 
+    /// Like `seek_k()`, but decodes the value with `FromLmdbBytesOwned`.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// # include!("src/example_helpers.rs");
+    /// # fn main() {
+    /// # let env = create_env();
+    /// # let db = dupdb(&env);
+    /// let txn = lmdb::WriteTransaction::new(&env).unwrap();
+    /// let fruit: String;
+    /// {
+    ///   let mut access = txn.access();
+    ///   let f = lmdb::put::Flags::empty();
+    ///   access.put(&db, "Fruit", "Apple", f).unwrap();
+    ///   access.put(&db, "Veggie", "Carrot", f).unwrap();
+    ///
+    ///   let mut cursor = txn.cursor(&db).unwrap();
+    ///   fruit = cursor.seek_k_owned(&access, "Fruit").unwrap();
+    ///   let (k, v): (String, Vec<u8>) =
+    ///     cursor.seek_range_k_owned(&access, "Fun").unwrap();
+    ///   assert_eq!(("Veggie", &b"Carrot"[..]), (&k[..], &v[..]));
+    /// }
+    /// txn.commit().unwrap();
+    /// // `fruit` outlives the accessor and transaction
+    /// assert_eq!("Apple", fruit);
+    /// # }
+    /// ```
+    #[inline]
+    pub fn seek_k_owned<'access, K : AsLmdbBytes + ?Sized,
+                        V : FromLmdbBytesOwned<'access>>
+        (&mut self, access: &'access ConstAccessor, key: &K) -> Result<V>
+    {
+        let (_, val) = try!(self.get_k_raw(
+            access, key, ffi::MDB_cursor_op::MDB_SET));
+        from_val_owned(access, &val)
+    }
+
     /// Positions the cursor at the first item of the given key.
     ///
     /// Returns the key and value of that item.
@@ -863,6 +1043,21 @@ impl<'txn,'db> Cursor<'txn,'db> {
         self.get_k_kv(access, key, ffi::MDB_cursor_op::MDB_SET_KEY)
     }
 
+    /// Like `seek_k_both()`, but decodes the key and value with
+    /// `FromLmdbBytesOwned`.
+    #[inline]
+    pub fn seek_k_both_owned<'access, Q : AsLmdbBytes + ?Sized,
+                             K : FromLmdbBytesOwned<'access>,
+                             V : FromLmdbBytesOwned<'access>>
+        (&mut self, access: &'access ConstAccessor, key: &Q)
+         -> Result<(K, V)>
+    {
+        let (key, val) = try!(self.get_k_raw(
+            access, key, ffi::MDB_cursor_op::MDB_SET_KEY));
+        Ok((try!(from_val_owned(access, &key)),
+            try!(from_val_owned(access, &val))))
+    }
+
     /// Positions the cursor at the first item whose key is greater than or
     /// equal to `key`.
     ///
@@ -902,6 +1097,21 @@ impl<'txn,'db> Cursor<'txn,'db> {
         self.get_k_kv(access, key, ffi::MDB_cursor_op::MDB_SET_RANGE)
     }
 
+    /// Like `seek_range_k()`, but decodes the key and value with
+    /// `FromLmdbBytesOwned`.
+    #[inline]
+    pub fn seek_range_k_owned<'access, Q : AsLmdbBytes + ?Sized,
+                              K : FromLmdbBytesOwned<'access>,
+                              V : FromLmdbBytesOwned<'access>>
+        (&mut self, access: &'access ConstAccessor, key: &Q)
+         -> Result<(K, V)>
+    {
+        let (key, val) = try!(self.get_k_raw(
+            access, key, ffi::MDB_cursor_op::MDB_SET_RANGE));
+        Ok((try!(from_val_owned(access, &key)),
+            try!(from_val_owned(access, &val))))
+    }
+
     /// Writes a single value through this cursor.
     ///
     /// By default, any item with the same key (if not `DUPSORT`) or any
@@ -945,7 +1155,8 @@ impl<'txn,'db> Cursor<'txn,'db> {
         unsafe {
             lmdb_call!(ffi::mdb_cursor_put(
                 self.cursor.0, &mut mv_key, &mut mv_val,
-                flags.bits()));
+                flags.bits()),
+                |err| self.key_context(err, "mdb_cursor_put", key));
         }
 
         Ok(())
@@ -999,7 +1210,8 @@ impl<'txn,'db> Cursor<'txn,'db> {
         unsafe {
             lmdb_call!(ffi::mdb_cursor_put(
                 self.cursor.0, &mut mv_key, &mut mv_val,
-                flags.bits() | ffi::MDB_CURRENT));
+                flags.bits() | ffi::MDB_CURRENT),
+                |err| self.key_context(err, "mdb_cursor_put", key));
         }
 
         Ok(())
@@ -1139,7 +1351,8 @@ impl<'txn,'db> Cursor<'txn,'db> {
 
         lmdb_call!(ffi::mdb_cursor_put(
             self.cursor.0, &mut mv_key, &mut out_val,
-            flags.bits() | ffi::MDB_RESERVE));
+            flags.bits() | ffi::MDB_RESERVE),
+            |err| self.key_context(err, "mdb_cursor_put", key));
 
         Ok(from_reserved(access, &out_val))
     }
@@ -1266,7 +1479,8 @@ impl<'txn,'db> Cursor<'txn,'db> {
 
         lmdb_call!(ffi::mdb_cursor_put(
             self.cursor.0, &mut mv_key, &mut out_val,
-            flags.bits() | ffi::MDB_RESERVE | ffi::MDB_CURRENT));
+            flags.bits() | ffi::MDB_RESERVE | ffi::MDB_CURRENT),
+            |err| self.key_context(err, "mdb_cursor_put", key));
 
         Ok(from_reserved(access, &out_val))
     }
@@ -1340,7 +1554,8 @@ impl<'txn,'db> Cursor<'txn,'db> {
         unsafe {
             lmdb_call!(ffi::mdb_cursor_put(
                 self.cursor.0, &mut mv_key, mv_vals.as_mut_ptr(),
-                flags.bits() | ffi::MDB_MULTIPLE));
+                flags.bits() | ffi::MDB_MULTIPLE),
+                |err| self.key_context(err, "mdb_cursor_put", key));
         }
 
         Ok(mv_vals[1].mv_size as usize)
@@ -1360,7 +1575,8 @@ impl<'txn,'db> Cursor<'txn,'db> {
         try!(assert_sensible_cursor(&*access, self));
 
         unsafe {
-            lmdb_call!(ffi::mdb_cursor_del(self.cursor.0, flags.bits()));
+            lmdb_call!(ffi::mdb_cursor_del(self.cursor.0, flags.bits()),
+                |err| self.context(err, "mdb_cursor_del", None));
         }
 
         Ok(())
@@ -1373,7 +1589,8 @@ impl<'txn,'db> Cursor<'txn,'db> {
     pub fn count(&mut self) -> Result<usize> {
         let mut raw: libc::size_t = 0;
         unsafe {
-            lmdb_call!(ffi::mdb_cursor_count(self.cursor.0, &mut raw));
+            lmdb_call!(ffi::mdb_cursor_count(self.cursor.0, &mut raw),
+                |err| self.context(err, "mdb_cursor_count", None));
         }
         Ok(raw as usize)
     }
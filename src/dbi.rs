@@ -14,11 +14,12 @@ use libc::c_int;
 
 use ffi;
 
-use env::{self, Environment};
+use env::{self, Environment, EnvRef};
 use error::{Error, Result};
 use mdb_vals::*;
 use traits::*;
 use tx::TxHandle;
+use typed::TypedDatabaseOptions;
 
 /// Flags used when opening databases.
 pub mod db {
@@ -225,6 +226,14 @@ pub mod db {
             ///   // Now we've read in all the values in sorted order.
             ///   assert_eq!(&['x' as u32, 'y' as u32, 'z' as u32],
             ///              &xyzzy[..]);
+            ///
+            ///   // Pages can also be copied out of the database directly.
+            ///   // The duplicate 'o' of "foo" is only stored once.
+            ///   cursor.seek_k::<str,U<u32>>(&access, "foo").unwrap();
+            ///   let foo: Vec<U<u32>> =
+            ///     cursor.get_multiple_owned(&access).unwrap();
+            ///   assert_eq!(vec!['f' as u32, 'o' as u32],
+            ///              foo.iter().map(|c| c.get()).collect::<Vec<_>>());
             /// }
             /// txn.commit().unwrap();
             /// # }
@@ -275,13 +284,13 @@ pub mod db {
 
 #[derive(Debug)]
 struct DbHandle<'a> {
-    env: &'a Environment,
+    env: EnvRef<'a>,
     dbi: ffi::MDB_dbi,
 }
 
 impl<'a> Drop for DbHandle<'a> {
     fn drop(&mut self) {
-        env::dbi_close(self.env, self.dbi);
+        env::dbi_close(&self.env, self.dbi);
     }
 }
 
@@ -298,7 +307,9 @@ impl<'a> Drop for DbHandle<'a> {
 ///
 /// ## Lifetime
 ///
-/// A `Database` must be strictly outlived by its `Environment`.
+/// A `Database` must be strictly outlived by its `Environment`, unless it was
+/// opened with an `Arc<Environment>`, in which case it keeps the environment
+/// alive itself and can be a `Database<'static>`.
 ///
 /// `'a` is covariant: given two lifetimes `'x` and `'y` where `'x: 'y`, a
 /// `&Database<'x>` will implicitly coerce to `&Database<'y>`.
@@ -434,25 +445,51 @@ impl DatabaseOptions {
         self.val_cmp = Some(DatabaseOptions::entry_cmp_as::<V>);
     }
 
-    /// Concisely creates a `DatabaseOptions` to configure a database to have a
-    /// 1:1 mapping using the given key type.
+    /// Concisely creates options to configure a database to have a 1:1
+    /// mapping from the given key type to the given value type.
     ///
     /// The flags always have `db::CREATE` set. If `K` is understood by LMDB as
     /// an integer, `db::INTEGERKEY` is set. Otherwise, unless `K` sorts
     /// properly via byte-string comparison, `sort_keys_as` is called to
     /// configure the database to use `K`'s `Ord` implementation.
-    pub fn create_map<K : LmdbOrdKey + ?Sized>() -> Self {
+    ///
+    /// The result carries `K` and `V`, so that `TypedDatabaseOptions::open()`
+    /// produces a `TypedDatabase<K, V>`. It dereferences to the untyped
+    /// options, so it can also be passed to `Database::open()`.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// # include!("src/example_helpers.rs");
+    /// # fn main() {
+    /// # let env = create_env();
+    /// use lmdb::Unaligned as U;
+    ///
+    /// let db = lmdb::DatabaseOptions::create_map::<U<u32>, str>()
+    ///   .open(&env, Some("example")).unwrap();
+    /// let txn = lmdb::WriteTransaction::new(&env).unwrap();
+    /// {
+    ///   let mut access = txn.access();
+    ///   db.put(&mut access, &U::new(1), "one", lmdb::put::Flags::empty())
+    ///     .unwrap();
+    ///   assert_eq!("one", db.get(&access, &U::new(1)).unwrap());
+    /// }
+    /// txn.commit().unwrap();
+    /// # }
+    /// ```
+    pub fn create_map<K : LmdbOrdKey + ?Sized, V : ?Sized>()
+                      -> TypedDatabaseOptions<K, V> {
         let mut this = DatabaseOptions::new(db::CREATE);
         if K::ordered_as_integer() {
             this.flags |= db::INTEGERKEY;
         } else if !K::ordered_by_bytes() {
             this.sort_keys_as::<K>();
         }
-        this
+        TypedDatabaseOptions::new(this)
     }
 
-    /// Concisely creates a `DatabaseOptions` to configure a database to have a
-    /// 1:M mapping using the given key and unsized value types.
+    /// Concisely creates options to configure a database to have a 1:M
+    /// mapping using the given key and unsized value types.
     ///
     /// The flags are configured as described with `create_map` with
     /// `db::DUPSORT` added. If `V` is understood by LMDB as an integer,
@@ -461,9 +498,9 @@ impl DatabaseOptions {
     /// implementation.
     pub fn create_multimap_unsized<K : LmdbOrdKey + ?Sized,
                                    V : LmdbOrdKey + ?Sized>
-        () -> Self
+        () -> TypedDatabaseOptions<K, V>
     {
-        let mut this = DatabaseOptions::create_map::<K>();
+        let mut this = DatabaseOptions::create_map::<K, V>();
         this.flags |= db::DUPSORT;
         if V::ordered_as_integer() {
             this.flags |= db::INTEGERDUP;
@@ -473,14 +510,40 @@ impl DatabaseOptions {
         this
     }
 
-    /// Concisely creates a `DatabaseOptions` to configure a database to have a
-    /// 1:M mapping using the given key and fixed-size value types.
+    /// Concisely creates options to configure a database to have a 1:M
+    /// mapping using the given key and fixed-size value types.
     ///
     /// This is the same as `create_multimap_unsized`, except that `DUPFIXED`
     /// is additionally set unconditionally.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// # include!("src/example_helpers.rs");
+    /// # fn main() {
+    /// # let env = create_env();
+    /// use lmdb::Unaligned as U;
+    ///
+    /// let db = lmdb::DatabaseOptions::create_multimap::<str, U<u16>>()
+    ///   .open(&env, Some("example")).unwrap();
+    /// let txn = lmdb::WriteTransaction::new(&env).unwrap();
+    /// {
+    ///   let mut access = txn.access();
+    ///   let f = lmdb::put::Flags::empty();
+    ///   db.put(&mut access, "port", &U::new(443), f).unwrap();
+    ///   db.put(&mut access, "port", &U::new(80), f).unwrap();
+    ///
+    ///   let mut cursor = db.cursor(&txn).unwrap();
+    ///   assert_eq!(80, cursor.seek_k(&access, "port").unwrap().get());
+    ///   assert_eq!(443, cursor.next_dup(&access).unwrap().1.get());
+    ///   assert_eq!(2, cursor.count().unwrap());
+    /// }
+    /// txn.commit().unwrap();
+    /// # }
+    /// ```
     pub fn create_multimap<K : LmdbOrdKey + ?Sized,
                            V : LmdbOrdKey + Sized>
-        () -> Self
+        () -> TypedDatabaseOptions<K, V>
     {
         let mut this = DatabaseOptions::create_multimap_unsized::<K, V>();
         this.flags |= db::DUPFIXED;
@@ -521,6 +584,11 @@ impl<'a> Database<'a> {
     /// One may not open the same database handle multiple times. Attempting to
     /// do so will result in the `Error::Reopened` error.
     ///
+    /// `env` is anything convertible into an `EnvRef`. Passing an
+    /// `&Environment` borrows the environment for the lifetime of the
+    /// returned `Database`; passing an `Arc<Environment>` makes the
+    /// `Database` share ownership of the environment instead.
+    ///
     /// ## Examples
     ///
     /// ### Open the default database with default options
@@ -556,6 +624,31 @@ impl<'a> Database<'a> {
     /// # }
     /// ```
     ///
+    /// ### Open a database which shares ownership of its environment
+    ///
+    /// ```
+    /// # include!("src/example_helpers.rs");
+    /// use std::sync::Arc;
+    /// use std::thread;
+    ///
+    /// # fn main() {
+    /// let env = Arc::new(create_env());
+    /// let db: Arc<lmdb::Database<'static>> = Arc::new(lmdb::Database::open(
+    ///   env.clone(), None, &lmdb::DatabaseOptions::defaults()).unwrap());
+    ///
+    /// let db2 = db.clone();
+    /// thread::spawn(move || {
+    ///   let txn = lmdb::WriteTransaction::new(db2.env().clone()).unwrap();
+    ///   txn.access().put(&*db2, "Latvia", "Rīga",
+    ///                    lmdb::put::Flags::empty()).unwrap();
+    ///   txn.commit().unwrap();
+    /// }).join().unwrap();
+    ///
+    /// let txn = lmdb::ReadTransaction::new(env.clone()).unwrap();
+    /// assert_eq!("Rīga", txn.access().get::<str,str>(&db, "Latvia").unwrap());
+    /// # }
+    /// ```
+    ///
     /// ### Trying to open the same database more than once
     /// ```
     /// # include!("src/example_helpers.rs");
@@ -571,19 +664,35 @@ impl<'a> Database<'a> {
     /// }
     /// # }
     /// ```
-    pub fn open(env: &'a Environment, name: Option<&str>,
-                options: &DatabaseOptions)
-                -> Result<Database<'a>> {
+    pub fn open<E : Into<EnvRef<'a>>>(env: E, name: Option<&str>,
+                                      options: &DatabaseOptions)
+                                      -> Result<Database<'a>> {
+        let env = env.into();
+        let raw = try!(Database::open_dbi(&env, name, options).map_err(
+            |err| env::error_context(&env, err, "mdb_dbi_open", name, None)));
+
+        Ok(Database {
+            db: DbHandle {
+                env: env,
+                dbi: raw,
+            }
+        })
+    }
+
+    // Opens the DBI for `open()`.
+    fn open_dbi(env: &Environment, name: Option<&str>,
+                options: &DatabaseOptions) -> Result<ffi::MDB_dbi> {
         let mut raw: ffi::MDB_dbi = 0;
         let name_cstr = match name {
             None => None,
             Some(s) => Some(try!(CString::new(s))),
         };
-        let raw = unsafe {
+        unsafe {
             // Locking the hash set here is also used to serialise calls to
             // `mdb_dbi_open()`, which are not permitted to be concurrent.
             let mut locked_dbis = env::env_open_dbis(env).lock()
                 .expect("open_dbis lock poisoned");
+            let _active = env::ActiveTxn::new(env);
 
             let mut raw_tx: *mut ffi::MDB_txn = ptr::null_mut();
             lmdb_call!(ffi::mdb_txn_begin(
@@ -596,6 +705,7 @@ impl<'a> Database<'a> {
             if !locked_dbis.insert(raw) {
                 return Err(Error::Reopened)
             }
+            env::set_dbi_name(env, raw, name);
 
             if let Some(fun) = options.key_cmp {
                 lmdb_call!(ffi::mdb_set_compare(raw_tx, raw, fun));
@@ -605,15 +715,8 @@ impl<'a> Database<'a> {
             }
 
             try!(wrapped_tx.commit());
-            raw
-        };
-
-        Ok(Database {
-            db: DbHandle {
-                env: env,
-                dbi: raw,
-            }
-        })
+            Ok(raw)
+        }
     }
 
     /// Deletes this database.
@@ -654,8 +757,12 @@ impl<'a> Database<'a> {
     /// # }
     /// ```
     pub fn delete(self) -> Result<()> {
-        try!(env::dbi_delete(self.db.env, self.db.dbi));
+        try!(env::dbi_delete(&self.db.env, self.db.dbi));
+        // The DBI is already closed, but we still need to release our
+        // reference to the environment.
+        let env = unsafe { ptr::read(&self.db.env) };
         mem::forget(self.db);
+        drop(env);
         Ok(())
     }
 
@@ -665,7 +772,7 @@ impl<'a> Database<'a> {
     /// If it matches, returns `Ok(())`; otherwise, returns `Err`.
     pub fn assert_same_env(&self, other_env: &Environment)
                            -> Result<()> {
-        if self.db.env as *const Environment !=
+        if &*self.db.env as *const Environment !=
             other_env as *const Environment
         {
             Err(Error::Mismatch)
@@ -674,6 +781,16 @@ impl<'a> Database<'a> {
         }
     }
 
+    /// Returns the `EnvRef` through which this database refers to its
+    /// environment.
+    ///
+    /// This is mainly useful with databases opened from an
+    /// `Arc<Environment>`, as cloning the result yields another handle which
+    /// shares ownership of the environment.
+    pub fn env(&self) -> &EnvRef<'a> {
+        &self.db.env
+    }
+
     /// Returns the underlying integer handle for this database.
     pub fn dbi(&self) -> ffi::MDB_dbi {
         self.db.dbi
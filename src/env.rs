@@ -6,18 +6,33 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
 
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::ffi::{CStr, CString};
 use std::mem;
+use std::ops::Deref;
 use std::ptr;
-use std::sync::Mutex;
+use std::str;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard, RwLock};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
 use libc::{self, c_char, c_int, c_uint, c_void};
 
 use ffi;
 use ffi2;
-use tx::TxHandle;
+use cursor;
+use dbi::db;
+use mdb_vals::{EMPTY_VAL, mdb_val_as_bytes};
+use error::{self, Error, ErrorContext, CONTEXT_KEY_LEN};
+use readers::{ReaderTracker, TrackedReader, TrackingConfig};
+use readers::{ReaderReaper, ReaperConfig};
+use tx::{self, TxHandle, ReadTransaction, WriteTransaction};
+use tx::{ConstAccessor, WriteAccessor};
 use ::{Fd, FileMode, Result};
 
+// The DBI of the unnamed database.
+const MAIN_DBI: ffi::MDB_dbi = 1;
+
 /// Flags used when opening an LMDB environment.
 pub mod open {
     use libc;
@@ -169,6 +184,84 @@ impl Drop for EnvHandle {
 #[derive(Debug)]
 pub struct EnvBuilder {
     env: EnvHandle,
+    map_growth: Option<MapGrowth>,
+    retry_policy: RetryPolicy,
+    resize_timeout: Duration,
+    error_context: bool,
+    reader_tracking: Option<TrackingConfig>,
+    reaper: Option<ReaperConfig>,
+}
+
+/// Policy for automatically growing the memory map of an environment.
+///
+/// See `EnvBuilder::set_map_growth()`.
+#[derive(Debug,Clone,Copy)]
+struct MapGrowth {
+    factor: f64,
+    max_size: usize,
+}
+
+/// Controls how `Environment::read()` and `Environment::write()` retry
+/// transactions which fail with a retryable error.
+///
+/// See `Error::is_retryable()` for which errors are considered retryable.
+/// Between attempts, the runner sleeps for a delay which starts at
+/// `initial_delay` and doubles after each attempt, up to `max_delay`. The
+/// exception is `MAP_RESIZED`, which is handled by adopting the new map size
+/// and retrying immediately. This happens even with `RetryPolicy::never()`,
+/// and does not count against `max_attempts`.
+///
+/// `TXN_FULL` is never retried, since the same work in a new transaction
+/// would fail the same way. It is returned immediately, and callers should
+/// split the work into smaller transactions (see `Error::is_txn_full()`).
+///
+/// ## Example
+///
+/// ```
+/// extern crate lmdb_zero as lmdb;
+/// # fn main() {
+/// use std::time::Duration;
+///
+/// let mut builder = lmdb::EnvBuilder::new().unwrap();
+/// builder.set_retry_policy(lmdb::RetryPolicy {
+///   max_attempts: 10,
+///   initial_delay: Duration::from_millis(5),
+///   max_delay: Duration::from_secs(1),
+/// });
+/// # }
+/// ```
+#[derive(Debug,Clone,Copy,PartialEq,Eq)]
+pub struct RetryPolicy {
+    /// The maximum number of times to run a transaction, including the first
+    /// attempt. Values of 0 and 1 both disable retrying.
+    pub max_attempts: u32,
+    /// The delay before the first retry.
+    pub initial_delay: Duration,
+    /// The upper bound on the delay between attempts.
+    pub max_delay: Duration,
+}
+
+impl RetryPolicy {
+    /// Returns a policy which never retries.
+    pub fn never() -> Self {
+        RetryPolicy {
+            max_attempts: 1,
+            initial_delay: Duration::from_millis(0),
+            max_delay: Duration::from_millis(0),
+        }
+    }
+}
+
+impl Default for RetryPolicy {
+    /// Returns a policy making up to 5 attempts, starting with a 1ms delay and
+    /// waiting at most 100ms between attempts.
+    fn default() -> Self {
+        RetryPolicy {
+            max_attempts: 5,
+            initial_delay: Duration::from_millis(1),
+            max_delay: Duration::from_millis(100),
+        }
+    }
 }
 
 impl EnvBuilder {
@@ -177,7 +270,15 @@ impl EnvBuilder {
         let mut env: *mut ffi::MDB_env = ptr::null_mut();
         unsafe {
             lmdb_call!(ffi::mdb_env_create(&mut env));
-            Ok(EnvBuilder { env: EnvHandle(env) })
+            Ok(EnvBuilder {
+                env: EnvHandle(env),
+                map_growth: None,
+                retry_policy: RetryPolicy::default(),
+                resize_timeout: Duration::from_secs(10),
+                error_context: false,
+                reader_tracking: None,
+                reaper: None,
+            })
         }
     }
 
@@ -238,6 +339,255 @@ impl EnvBuilder {
         Ok(())
     }
 
+    /// Enable automatic growth of the memory map by `Environment::write()`.
+    ///
+    /// When a write transaction run through `Environment::write()` fails with
+    /// `MAP_FULL`, the map size is multiplied by `factor` (rounded up to a
+    /// whole number of pages, and capped at `max_size`) and the transaction is
+    /// retried. Once the map size has reached `max_size`, `MAP_FULL` is
+    /// returned to the caller as usual.
+    ///
+    /// Growth is disabled by default. Transactions created directly through
+    /// `WriteTransaction::new()` are not affected by this setting.
+    ///
+    /// `factor` must be greater than 1; otherwise, `EINVAL` is returned.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// extern crate lmdb_zero as lmdb;
+    /// extern crate tempdir;
+    ///
+    /// # fn main() {
+    /// # let tmp = tempdir::TempDir::new_in(".", "lmdbzero").unwrap();
+    /// # let path = tmp.path().to_str().unwrap();
+    /// let mut builder = lmdb::EnvBuilder::new().unwrap();
+    /// // Start out tiny, but allow growing up to 64MB.
+    /// builder.set_mapsize(64 * 1024).unwrap();
+    /// builder.set_map_growth(2.0, 64 * 1024 * 1024).unwrap();
+    /// let env = unsafe {
+    ///   builder.open(path, lmdb::open::Flags::empty(), 0o600).unwrap()
+    /// };
+    /// let db = lmdb::Database::open(
+    ///   &env, None, &lmdb::DatabaseOptions::defaults()).unwrap();
+    ///
+    /// // 1MB of data would not have fit in the initial map.
+    /// env.write(|_, access| {
+    ///   for i in 0..1024u32 {
+    ///     try!(access.put(&db, &i, &[0u8;1024][..],
+    ///                     lmdb::put::Flags::empty()));
+    ///   }
+    ///   Ok(())
+    /// }).unwrap();
+    /// assert!(env.info().unwrap().mapsize > 1024 * 1024);
+    /// # }
+    /// ```
+    pub fn set_map_growth(&mut self, factor: f64, max_size: usize)
+                          -> Result<()> {
+        if factor.is_nan() || factor <= 1.0 {
+            return Err(Error::Os(libc::EINVAL));
+        }
+
+        self.map_growth = Some(MapGrowth {
+            factor: factor,
+            max_size: max_size,
+        });
+        Ok(())
+    }
+
+    /// Set the policy used by `Environment::read()` and `Environment::write()`
+    /// to retry transactions which fail with a retryable error.
+    ///
+    /// The default is `RetryPolicy::default()`.
+    pub fn set_retry_policy(&mut self, policy: RetryPolicy) {
+        self.retry_policy = policy;
+    }
+
+    /// Set how long `Environment::write()` and `Environment::read()` wait
+    /// for other transactions in this process to end before resizing the
+    /// map.
+    ///
+    /// Resizing the map, either to grow it (see `set_map_growth()`) or to
+    /// adopt a size set by another process after `MAP_RESIZED`, requires
+    /// that no transactions are active in this process, and new
+    /// transactions are held up while it waits. If other transactions are
+    /// still active after `timeout`, the resize is abandoned, the held up
+    /// transactions proceed, and the call fails with
+    /// `Error::ResizeTimeout`. This includes the case where the calling
+    /// thread itself holds another transaction, which would otherwise wait
+    /// forever.
+    ///
+    /// The default is 10 seconds.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// extern crate lmdb_zero as lmdb;
+    /// extern crate tempdir;
+    ///
+    /// # fn main() {
+    /// use std::time::Duration;
+    ///
+    /// # let tmp = tempdir::TempDir::new_in(".", "lmdbzero").unwrap();
+    /// # let path = tmp.path().to_str().unwrap();
+    /// let mut builder = lmdb::EnvBuilder::new().unwrap();
+    /// builder.set_mapsize(64 * 1024).unwrap();
+    /// builder.set_map_growth(2.0, 64 * 1024 * 1024).unwrap();
+    /// builder.set_resize_timeout(Duration::from_millis(50));
+    /// let env = unsafe {
+    ///   builder.open(path, lmdb::open::Flags::empty(), 0o600).unwrap()
+    /// };
+    /// let db = lmdb::Database::open(
+    ///   &env, None, &lmdb::DatabaseOptions::defaults()).unwrap();
+    ///
+    /// // The map cannot be grown while this transaction is open
+    /// let reader = lmdb::ReadTransaction::new(&env).unwrap();
+    /// let result = env.write(|_, access| {
+    ///   for i in 0..1024u32 {
+    ///     try!(access.put(&db, &i, &[0u8;1024][..],
+    ///                     lmdb::put::Flags::empty()));
+    ///   }
+    ///   Ok(())
+    /// });
+    /// assert_eq!(Err(lmdb::Error::ResizeTimeout), result);
+    ///
+    /// drop(reader);
+    /// env.write(|_, access| access.put(
+    ///   &db, &0u32, "fits", lmdb::put::Flags::empty())).unwrap();
+    /// # }
+    /// ```
+    pub fn set_resize_timeout(&mut self, timeout: Duration) {
+        self.resize_timeout = timeout;
+    }
+
+    /// Sets whether errors from common operations are wrapped in
+    /// `Error::Context`, describing the operation, database and key
+    /// involved. See `ErrorContext` for the operations covered.
+    ///
+    /// This is disabled by default, since it allocates for every error, and
+    /// code matching on errors directly must then go through
+    /// `Error::root()`. `NotFound` is never wrapped, since it is the normal
+    /// result of reaching the end of a cursor or looking up a missing key.
+    pub fn set_error_context(&mut self, enabled: bool) {
+        self.error_context = enabled;
+    }
+
+    /// Enables tracking of the read transactions open on the environment,
+    /// which can then be queried with `Environment::tracked_readers()` and
+    /// `Environment::old_readers()`.
+    ///
+    /// Long-lived read transactions prevent LMDB from reusing the pages
+    /// freed since their snapshot, causing the file to grow. Tracking helps
+    /// to find where they come from: each transaction records when it was
+    /// opened, a label given with `ReadTransaction::set_label()` and, if
+    /// `capture_backtraces` is true, a backtrace of where it was opened.
+    /// Capturing backtraces is expensive, so should be reserved for
+    /// debugging.
+    ///
+    /// Tracking is disabled by default.
+    pub fn set_reader_tracking(&mut self, capture_backtraces: bool) {
+        self.reader_tracking.get_or_insert_with(TrackingConfig::new)
+            .set_capture_backtraces(capture_backtraces);
+    }
+
+    /// Registers `callback` to be invoked for each read transaction which is
+    /// still open `threshold` after it was opened.
+    ///
+    /// This enables reader tracking, as with `set_reader_tracking(false)` if
+    /// it was not already enabled. The callback is invoked at most once per
+    /// transaction, from a background thread which is stopped when the
+    /// environment is dropped. A `threshold` too large to be added to the
+    /// current time, such as `Duration::MAX`, is never reached.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// extern crate lmdb_zero as lmdb;
+    /// extern crate tempdir;
+    ///
+    /// # fn main() {
+    /// use std::sync::mpsc;
+    /// use std::time::Duration;
+    ///
+    /// # let tmp = tempdir::TempDir::new_in(".", "lmdbzero").unwrap();
+    /// # let path = tmp.path().to_str().unwrap();
+    /// let (sender, receiver) = mpsc::channel();
+    /// let sender = std::sync::Mutex::new(sender);
+    /// let mut builder = lmdb::EnvBuilder::new().unwrap();
+    /// builder.set_old_reader_callback(
+    ///   Duration::from_millis(10), move |reader: &lmdb::TrackedReader| {
+    ///     let _ = sender.lock().unwrap().send(reader.label.clone());
+    ///   });
+    /// let env = unsafe {
+    ///   builder.open(path, lmdb::open::Flags::empty(), 0o600).unwrap()
+    /// };
+    ///
+    /// let txn = lmdb::ReadTransaction::new(&env).unwrap();
+    /// txn.set_label("report generator");
+    /// assert_eq!(Some("report generator".to_owned()),
+    ///            receiver.recv_timeout(Duration::from_secs(10)).unwrap());
+    /// # }
+    /// ```
+    pub fn set_old_reader_callback<F>(&mut self, threshold: Duration,
+                                      callback: F)
+    where F : Fn (&TrackedReader) + Send + Sync + 'static {
+        self.reader_tracking.get_or_insert_with(TrackingConfig::new)
+            .set_old_reader_callback(threshold, Box::new(callback));
+    }
+
+    /// Starts a background thread which calls `Environment::reader_check()`
+    /// every `interval`, clearing the reader lock table entries left behind
+    /// by processes which exited without closing their transactions.
+    ///
+    /// `callback` is invoked from the background thread with the result of
+    /// each check which cleared at least one slot or failed. The thread is
+    /// stopped when the environment is dropped. An `interval` too large to be
+    /// added to the current time, such as `Duration::MAX`, means the check
+    /// never runs.
+    ///
+    /// This is disabled by default.
+    ///
+    /// `interval` must not be zero; otherwise, `EINVAL` is returned.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// extern crate lmdb_zero as lmdb;
+    /// extern crate tempdir;
+    ///
+    /// # fn main() {
+    /// use std::time::Duration;
+    ///
+    /// # let tmp = tempdir::TempDir::new_in(".", "lmdbzero").unwrap();
+    /// # let path = tmp.path().to_str().unwrap();
+    /// let mut builder = lmdb::EnvBuilder::new().unwrap();
+    /// builder.set_reader_reaper(Duration::from_secs(30), |result| {
+    ///   match result {
+    ///     Ok(cleared) => println!("Cleared {} stale readers", cleared),
+    ///     Err(err) => println!("Failed to check readers: {}", err),
+    ///   }
+    /// }).unwrap();
+    /// // A zero interval would check continuously
+    /// assert!(builder.set_reader_reaper(Duration::from_secs(0), |_| ())
+    ///         .is_err());
+    /// let env = unsafe {
+    ///   builder.open(path, lmdb::open::Flags::empty(), 0o600).unwrap()
+    /// };
+    /// // Use `env`...
+    /// drop(env); // The background thread stops here
+    /// # }
+    /// ```
+    pub fn set_reader_reaper<F>(&mut self, interval: Duration, callback: F)
+                                -> Result<()>
+    where F : Fn (Result<i32>) + Send + Sync + 'static {
+        if Duration::from_secs(0) == interval {
+            return Err(Error::Os(libc::EINVAL));
+        }
+
+        self.reaper = Some(ReaperConfig::new(interval, Box::new(callback)));
+        Ok(())
+    }
+
     /// Opens the file or directory at `path` with the given `flags` and, on
     /// UNIX, permissions given by `mode`.
     ///
@@ -269,9 +619,24 @@ impl EnvBuilder {
         let path_cstr = try!(CString::new(path));
         lmdb_call!(ffi::mdb_env_open(
             self.env.0, path_cstr.as_ptr(), flags.bits(), mode));
+        let env_ptr = self.env.0;
         Ok(Environment {
+            _reaper: self.reaper.map(
+                |config| ReaderReaper::new(env_ptr, config)),
             env: self.env,
             open_dbis: Mutex::new(HashSet::new()),
+            dbi_names: RwLock::new(HashMap::new()),
+            active_txns: ActiveTxns {
+                count: AtomicUsize::new(0),
+                resizing: AtomicBool::new(false),
+                lock: Mutex::new(()),
+                cond: Condvar::new(),
+            },
+            map_growth: self.map_growth,
+            retry_policy: self.retry_policy,
+            resize_timeout: self.resize_timeout,
+            error_context: self.error_context,
+            reader_tracker: self.reader_tracking.map(ReaderTracker::new),
         })
     }
 }
@@ -279,10 +644,119 @@ impl EnvBuilder {
 /// An LMDB environment which has been opened to a file.
 #[derive(Debug)]
 pub struct Environment {
+    // The reaper thread uses `env`, so must be declared (and thus stopped)
+    // before it.
+    _reaper: Option<ReaderReaper>,
     env: EnvHandle,
     // Track what DBIs are currently in use, so that an open() call that tries
     // to duplicate one fails.
     open_dbis: Mutex<HashSet<ffi::MDB_dbi>>,
+    // The names of the open named databases, for `ErrorContext`. This is
+    // separate from `open_dbis` so that looking up a name never waits on a
+    // thread which holds `open_dbis` while waiting on other transactions.
+    // Only held briefly, and never while taking another lock.
+    dbi_names: RwLock<HashMap<ffi::MDB_dbi, String>>,
+    // Track how many transactions this process has active, so that the map
+    // can be resized when there are none.
+    active_txns: ActiveTxns,
+    map_growth: Option<MapGrowth>,
+    retry_policy: RetryPolicy,
+    resize_timeout: Duration,
+    error_context: bool,
+    reader_tracker: Option<ReaderTracker>,
+}
+
+#[derive(Debug)]
+struct ActiveTxns {
+    count: AtomicUsize,
+    // Set while a resize is pending or in progress. New transactions wait for
+    // this to be cleared before starting.
+    resizing: AtomicBool,
+    // Protects nothing by itself; used with `cond` to wait for `count` to
+    // reach zero or `resizing` to be cleared.
+    lock: Mutex<()>,
+    cond: Condvar,
+}
+
+/// A handle on an `Environment` which is either borrowed or shared through an
+/// `Arc`.
+///
+/// Everything which needs to hold onto an environment (`Database::open()`,
+/// `ReadTransaction::new()` and `WriteTransaction::new()`) accepts anything
+/// convertible into an `EnvRef`. Passing a plain `&Environment` results in
+/// the usual borrowed handle whose lifetime is bound by the reference, with
+/// no overhead beyond the extra discriminant. Passing an `Arc<Environment>`
+/// instead results in a handle that keeps the environment alive by itself,
+/// and which can therefore have a `'static` lifetime and be stored in
+/// long-lived structures without any lifetime parameters.
+///
+/// ## Example
+///
+/// ```
+/// # include!("src/example_helpers.rs");
+/// use std::sync::Arc;
+///
+/// // Neither member has a lifetime tied to anything else.
+/// struct Store {
+///   env: Arc<lmdb::Environment>,
+///   db: lmdb::Database<'static>,
+/// }
+///
+/// impl Store {
+///   fn snapshot(&self) -> lmdb::ReadTransaction<'static> {
+///     lmdb::ReadTransaction::new(self.env.clone()).unwrap()
+///   }
+/// }
+///
+/// # fn main() {
+/// let env = Arc::new(create_env());
+/// let db = lmdb::Database::open(
+///   env.clone(), None, &lmdb::DatabaseOptions::defaults()).unwrap();
+/// let store = Store { env: env, db: db };
+///
+/// {
+///   let txn = lmdb::WriteTransaction::new(store.env.clone()).unwrap();
+///   txn.access().put(&store.db, "Germany", "Berlin",
+///                    lmdb::put::Flags::empty()).unwrap();
+///   txn.commit().unwrap();
+/// }
+///
+/// let txn = store.snapshot();
+/// // The transaction keeps the environment alive on its own, so it can
+/// // outlive the `Store` it came from.
+/// drop(store);
+/// # let _ = txn.id();
+/// # }
+/// ```
+#[derive(Debug,Clone)]
+pub enum EnvRef<'a> {
+    /// The environment is borrowed for the lifetime `'a`.
+    Borrowed(&'a Environment),
+    /// The environment is kept alive by a strong `Arc` reference.
+    Shared(Arc<Environment>),
+}
+
+impl<'a> Deref for EnvRef<'a> {
+    type Target = Environment;
+
+    fn deref(&self) -> &Environment {
+        match *self {
+            EnvRef::Borrowed(env) => env,
+            EnvRef::Shared(ref env) => env,
+        }
+    }
+}
+
+impl<'a> From<&'a Environment> for EnvRef<'a> {
+    fn from(env: &'a Environment) -> Self {
+        EnvRef::Borrowed(env)
+    }
+}
+
+impl<'a> From<Arc<Environment>> for EnvRef<'a> {
+    fn from(env: Arc<Environment>) -> Self {
+        EnvRef::Shared(env)
+    }
 }
 
 /// Statistics information about an environment.
@@ -315,6 +789,72 @@ impl From<ffi::MDB_stat> for Stat {
     }
 }
 
+/// Information about a named database, as returned by
+/// `Environment::databases()`.
+#[derive(Debug,Clone)]
+pub struct DatabaseInfo {
+    /// The name of the database
+    pub name: String,
+    /// The flags the database was created with
+    pub flags: db::Flags,
+    /// Statistics about the database
+    pub stat: Stat,
+}
+
+/// An entry in the reader lock table of an environment, as returned by
+/// `Environment::readers()`.
+#[derive(Debug,Clone,Copy,PartialEq,Eq,Hash)]
+pub struct ReaderInfo {
+    /// ID of the process owning the slot
+    pub pid: u32,
+    /// ID of the thread owning the slot, as reported by LMDB (on UNIX, the
+    /// `pthread_t` of the thread)
+    pub thread: usize,
+    /// ID of the snapshot the reader is using (see `ConstTransaction::id()`),
+    /// or `None` if the slot is not currently in use by a transaction, for
+    /// example because it was reset
+    pub txnid: Option<usize>,
+}
+
+// Parses one line printed by `mdb_reader_list()`, which has the format
+// `"%10d %zx %zu"` for the pid, thread and txnid, with a txnid of `-` for
+// idle slots. Returns `None` for the header and other lines.
+fn parse_reader(line: &str) -> Option<ReaderInfo> {
+    let fields: Vec<&str> = line.split_whitespace().collect();
+    if 3 != fields.len() {
+        return None;
+    }
+
+    let txnid = if "-" == fields[2] {
+        None
+    } else {
+        match fields[2].parse() {
+            Ok(txnid) => Some(txnid),
+            Err(_) => return None,
+        }
+    };
+
+    match (fields[0].parse(), usize::from_str_radix(fields[1], 16)) {
+        (Ok(pid), Ok(thread)) => Some(ReaderInfo {
+            pid: pid,
+            thread: thread,
+            txnid: txnid,
+        }),
+        _ => None,
+    }
+}
+
+// Callback for `mdb_reader_list()`, collecting each line into the
+// `Vec<String>` pointed to by `ctx`.
+extern fn collect_reader_line(msg: *const c_char, ctx: *const c_void)
+                              -> c_int {
+    unsafe {
+        let lines = &mut *(ctx as *mut Vec<String>);
+        lines.push(CStr::from_ptr(msg).to_string_lossy().into_owned());
+    }
+    0
+}
+
 /// Configuration information about an environment.
 #[derive(Debug,Clone,Copy)]
 pub struct EnvInfo {
@@ -555,6 +1095,351 @@ impl Environment {
         }
     }
 
+    /// Runs `f` in a new read-only transaction.
+    ///
+    /// `f` is passed the transaction and its accessor. Since the accessor has
+    /// already been taken, `f` must not call `access()` on the transaction
+    /// itself. The transaction ends when this call returns, so `f` must copy
+    /// out anything it wants to keep.
+    ///
+    /// If beginning the transaction or `f` itself fails with an error for
+    /// which `Error::is_retryable()` returns true, `f` is rerun in a new
+    /// transaction according to the environment's `RetryPolicy`. See
+    /// `write()` for notes about `MAP_RESIZED`.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// # include!("src/example_helpers.rs");
+    /// # fn main() {
+    /// # let env = create_env();
+    /// # let db = defdb(&env);
+    /// env.write(|_, access| access.put(
+    ///   &db, "Latvia", "Rīga", lmdb::put::Flags::empty())).unwrap();
+    ///
+    /// let capital = env.read(|_, access| {
+    ///   access.get::<str,str>(&db, "Latvia").map(|s| s.to_owned())
+    /// }).unwrap();
+    /// assert_eq!("Rīga", capital);
+    /// # }
+    /// ```
+    pub fn read<T, F : FnMut (&ReadTransaction, &ConstAccessor) -> Result<T>>
+        (&self, mut f: F) -> Result<T>
+    {
+        self.run_txn(|| {
+            let txn = try!(ReadTransaction::new(self));
+            let access = txn.access();
+            f(&txn, &access)
+        })
+    }
+
+    /// Runs `f` in a new write transaction, committing it if `f` returns `Ok`
+    /// and aborting it otherwise.
+    ///
+    /// `f` is passed the transaction and its accessor. Since the accessor has
+    /// already been taken, `f` must not call `access()` on the transaction
+    /// itself.
+    ///
+    /// `f` may be invoked more than once, as the following cause the
+    /// transaction to be aborted and `f` to be rerun in a fresh transaction:
+    ///
+    /// - Any error for which `Error::is_retryable()` returns true, subject to
+    ///   the environment's `RetryPolicy`. `TXN_FULL` is not retryable and is
+    ///   returned immediately.
+    ///
+    /// - `MAP_RESIZED`, which indicates that another process grew the map
+    ///   beyond the size known to this one. The new size is adopted before
+    ///   retrying. This does not count against the retry policy.
+    ///
+    /// - `MAP_FULL`, if growth was enabled with
+    ///   `EnvBuilder::set_map_growth()` and the map has not yet reached the
+    ///   configured maximum size. The map is grown before retrying. This does
+    ///   not count against the retry policy.
+    ///
+    /// Resizing the map requires that no other transactions are active in
+    /// this process. New transactions block while a resize is pending, and
+    /// the resize waits for all existing transactions to end, up to the
+    /// timeout set with `EnvBuilder::set_resize_timeout()`. It then fails
+    /// with `Error::ResizeTimeout`. In particular, calling this function
+    /// while the current thread holds any other transaction on this
+    /// environment fails this way whenever the map needs to be resized.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// # include!("src/example_helpers.rs");
+    /// # fn main() {
+    /// # let env = create_env();
+    /// # let db = defdb(&env);
+    /// let f = lmdb::put::Flags::empty();
+    /// env.write(|_, access| {
+    ///   try!(access.put(&db, "Germany", "Berlin", f));
+    ///   access.put(&db, "Latvia", "Rīga", f)
+    /// }).unwrap();
+    ///
+    /// // An error aborts the transaction.
+    /// assert!(env.write(|_, access| {
+    ///   try!(access.put(&db, "France", "Paris", f));
+    ///   access.put(&db, "Latvia", "Jelgava", lmdb::put::NOOVERWRITE)
+    /// }).is_err());
+    ///
+    /// let txn = lmdb::ReadTransaction::new(&env).unwrap();
+    /// let access = txn.access();
+    /// assert_eq!("Rīga", access.get::<str,str>(&db, "Latvia").unwrap());
+    /// assert!(access.get::<str,str>(&db, "France").is_err());
+    /// # }
+    /// ```
+    pub fn write<T, F : FnMut (&WriteTransaction, &mut WriteAccessor)
+                              -> Result<T>>
+        (&self, mut f: F) -> Result<T>
+    {
+        self.run_txn(|| {
+            let txn = try!(WriteTransaction::new(self));
+            let result = {
+                let mut access = txn.access();
+                f(&txn, &mut access)
+            };
+            match result {
+                Ok(val) => txn.commit().map(|_| val),
+                Err(err) => Err(err),
+            }
+        })
+    }
+
+    // Runs `attempt` until it succeeds or fails with an error which cannot be
+    // handled by growing the map or retrying.
+    fn run_txn<T, F : FnMut () -> Result<T>>(&self, mut attempt: F)
+                                            -> Result<T> {
+        let policy = self.retry_policy;
+        let mut attempts = 1;
+        let mut delay = policy.initial_delay;
+        loop {
+            let mapsize = try!(self.info()).mapsize;
+            match attempt() {
+                Err(ref err)
+                if Error::MapFull == *err.root() &&
+                    self.can_grow_map(mapsize) =>
+                    try!(self.resize_map(|| self.grown_mapsize(mapsize))),
+                Err(ref err) if Error::MapResized == *err.root() =>
+                    try!(self.resize_map(|| Ok(0))),
+                Err(ref err)
+                if err.is_retryable() && attempts < policy.max_attempts => {
+                    attempts += 1;
+                    thread::sleep(delay);
+                    delay = delay.checked_mul(2).map_or(
+                        policy.max_delay, |d| d.min(policy.max_delay));
+                },
+                result => return result,
+            }
+        }
+    }
+
+    fn can_grow_map(&self, mapsize: usize) -> bool {
+        match self.map_growth {
+            Some(growth) => mapsize < growth.max_size,
+            None => false,
+        }
+    }
+
+    // Returns the size to grow the map to given it was `mapsize` when
+    // `MAP_FULL` was encountered, or 0 (ie, keep the current size) if another
+    // thread has already grown it in the meantime.
+    fn grown_mapsize(&self, mapsize: usize) -> Result<usize> {
+        let growth = self.map_growth.expect("grown_mapsize() without growth");
+        let info = try!(self.info());
+        if info.mapsize > mapsize {
+            return Ok(0);
+        }
+
+        let page_size = try!(self.stat()).psize as usize;
+        let mut grown = (mapsize as f64 * growth.factor) as usize;
+        let partial_page = grown % page_size;
+        if partial_page > 0 {
+            grown += page_size - partial_page;
+        }
+        Ok(if grown > growth.max_size { growth.max_size } else { grown })
+    }
+
+    // Waits for all transactions in this process to end, then sets the map
+    // size to the result of `size`. New transactions are blocked until this
+    // completes. Fails with `ResizeTimeout` if transactions are still active
+    // after `resize_timeout`.
+    fn resize_map<F : FnOnce () -> Result<usize>>(&self, size: F)
+                                                 -> Result<()> {
+        // A timeout too large to represent is as good as none.
+        let deadline = Instant::now().checked_add(self.resize_timeout);
+        let active = &self.active_txns;
+        let mut locked = active.lock.lock()
+            .expect("active_txns lock poisoned");
+        // Only one resize may be in progress at a time.
+        while active.resizing.load(Ordering::SeqCst) {
+            locked = match wait_until(&active.cond, locked, deadline) {
+                Some(locked) => locked,
+                None => return Err(Error::ResizeTimeout),
+            };
+        }
+        active.resizing.store(true, Ordering::SeqCst);
+        while 0 != active.count.load(Ordering::SeqCst) {
+            locked = match wait_until(&active.cond, locked, deadline) {
+                Some(locked) => locked,
+                None => {
+                    // Let the transactions held up by this resize proceed.
+                    active.resizing.store(false, Ordering::SeqCst);
+                    active.cond.notify_all();
+                    return Err(Error::ResizeTimeout);
+                },
+            };
+        }
+
+        let result = size().and_then(
+            |size| unsafe { self.set_mapsize(size) });
+
+        active.resizing.store(false, Ordering::SeqCst);
+        active.cond.notify_all();
+        result
+    }
+
+    /// Returns the names of all named databases in this environment, in the
+    /// order of the unnamed database (by default, sorted bytewise).
+    ///
+    /// Named databases are stored as keys in the unnamed database. This reads
+    /// the unnamed database in a new `ReadTransaction`, skipping keys which
+    /// are not databases (ie, data stored in the unnamed database itself) and
+    /// keys which are not valid UTF-8, since such databases could not be
+    /// opened with `Database::open()` anyway.
+    ///
+    /// Each database not already open is opened for the duration of the
+    /// call, so this fails with `Error::DbsFull` if `maxdbs` is not large
+    /// enough to hold all of them at once.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// extern crate lmdb_zero as lmdb;
+    /// extern crate tempdir;
+    ///
+    /// # fn main() {
+    /// # let tmp = tempdir::TempDir::new_in(".", "lmdbzero").unwrap();
+    /// # let path = tmp.path().to_str().unwrap();
+    /// let mut builder = lmdb::EnvBuilder::new().unwrap();
+    /// builder.set_maxdbs(4).unwrap();
+    /// # // Listing must also work with error context, which looks up
+    /// # // database names
+    /// # builder.set_error_context(true);
+    /// let env = unsafe {
+    ///   builder.open(path, lmdb::open::Flags::empty(), 0o600).unwrap()
+    /// };
+    /// for name in &["people", "cities"] {
+    ///   lmdb::Database::open(&env, Some(name), &lmdb::DatabaseOptions::new(
+    ///     lmdb::db::CREATE)).unwrap();
+    /// }
+    /// // Plain data in the unnamed database is not listed
+    /// let main = lmdb::Database::open(
+    ///   &env, None, &lmdb::DatabaseOptions::defaults()).unwrap();
+    /// let txn = lmdb::WriteTransaction::new(&env).unwrap();
+    /// txn.access().put(&main, "version", "1", lmdb::put::Flags::empty())
+    ///   .unwrap();
+    /// txn.commit().unwrap();
+    ///
+    /// assert_eq!(vec!["cities", "people"], env.database_names().unwrap());
+    /// # }
+    /// ```
+    pub fn database_names(&self) -> Result<Vec<String>> {
+        self.each_database(|name, _, _| Ok(name))
+    }
+
+    /// Like `database_names()`, but also returns the flags and statistics of
+    /// each database.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// extern crate lmdb_zero as lmdb;
+    /// extern crate tempdir;
+    ///
+    /// # fn main() {
+    /// # let tmp = tempdir::TempDir::new_in(".", "lmdbzero").unwrap();
+    /// # let path = tmp.path().to_str().unwrap();
+    /// let mut builder = lmdb::EnvBuilder::new().unwrap();
+    /// builder.set_maxdbs(4).unwrap();
+    /// let env = unsafe {
+    ///   builder.open(path, lmdb::open::Flags::empty(), 0o600).unwrap()
+    /// };
+    /// let db = lmdb::Database::open(
+    ///   &env, Some("tags"), &lmdb::DatabaseOptions::new(
+    ///     lmdb::db::CREATE | lmdb::db::DUPSORT)).unwrap();
+    /// let txn = lmdb::WriteTransaction::new(&env).unwrap();
+    /// {
+    ///   let mut access = txn.access();
+    ///   let f = lmdb::put::Flags::empty();
+    ///   access.put(&db, "fruit", "apple", f).unwrap();
+    ///   access.put(&db, "fruit", "orange", f).unwrap();
+    /// }
+    /// txn.commit().unwrap();
+    ///
+    /// let dbs = env.databases().unwrap();
+    /// assert_eq!(1, dbs.len());
+    /// assert_eq!("tags", dbs[0].name);
+    /// assert!(dbs[0].flags.contains(lmdb::db::DUPSORT));
+    /// assert_eq!(2, dbs[0].stat.entries);
+    /// # }
+    /// ```
+    pub fn databases(&self) -> Result<Vec<DatabaseInfo>> {
+        self.each_database(|name, txn, dbi| unsafe {
+            let mut flags: c_uint = 0;
+            lmdb_call!(ffi::mdb_dbi_flags(txn, dbi, &mut flags));
+            let mut raw: ffi::MDB_stat = mem::zeroed();
+            lmdb_call!(ffi::mdb_stat(txn, dbi, &mut raw));
+            Ok(DatabaseInfo {
+                name: name,
+                flags: db::Flags::from_bits_truncate(flags),
+                stat: raw.into(),
+            })
+        })
+    }
+
+    // Opens each named database in a read transaction, collecting the results
+    // of passing its name, the raw transaction and its DBI to `f`.
+    fn each_database<T, F>(&self, mut f: F) -> Result<Vec<T>>
+    where F : FnMut (String, *mut ffi::MDB_txn, ffi::MDB_dbi) -> Result<T> {
+        // `mdb_dbi_open()` may not be called concurrently with itself, so
+        // serialise with `Database::open()`. The lock must be taken before
+        // the transaction is started, in the same order as there. Nothing
+        // below may go through `dbi_error_context()` or otherwise take
+        // `open_dbis` again.
+        let _locked_dbis = self.open_dbis.lock()
+            .expect("open_dbis lock poisoned");
+        let txn = try!(ReadTransaction::new(self));
+        let raw_txn = tx::txn_ptr(&txn);
+        let mut ret = Vec::new();
+
+        for key in try!(main_db_keys(&txn)) {
+            let name = match String::from_utf8(key).ok()
+                .and_then(|name| CString::new(name).ok())
+            {
+                Some(name) => name,
+                None => continue,
+            };
+
+            // Any handle opened here which was not already open is closed
+            // automatically when the transaction ends.
+            let mut dbi: ffi::MDB_dbi = 0;
+            match unsafe {
+                ffi::mdb_dbi_open(raw_txn, name.as_ptr(), 0, &mut dbi)
+            } {
+                0 => (),
+                // Not a database, just a key in the unnamed database
+                error::INCOMPATIBLE => continue,
+                code => return Err(Error::from_code(code)),
+            }
+
+            let name = name.into_string().expect("name was already UTF-8");
+            ret.push(try!(f(name, raw_txn, dbi)));
+        }
+
+        Ok(ret)
+    }
+
     /// Check for stale entries in the reader lock table.
     ///
     /// Returns the number of stale slots that were cleared.
@@ -565,6 +1450,126 @@ impl Environment {
         }
         Ok(raw as i32)
     }
+
+    /// Returns the entries in the reader lock table of this environment.
+    ///
+    /// This corresponds to `mdb_reader_list()`. The table includes the
+    /// readers of all processes using the environment. Readers holding a
+    /// snapshot with a `txnid` far behind `EnvInfo::last_txnid` prevent the
+    /// pages freed since that snapshot from being reused, causing the file
+    /// to grow.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// # include!("src/example_helpers.rs");
+    /// # fn main() {
+    /// # let env = create_env();
+    /// let txn = lmdb::ReadTransaction::new(&env).unwrap();
+    /// let readers = env.readers().unwrap();
+    /// assert!(readers.iter().any(
+    ///   |r| std::process::id() == r.pid && Some(txn.id()) == r.txnid));
+    ///
+    /// // A reset transaction keeps its slot, but holds no snapshot
+    /// let _reset = txn.reset();
+    /// let readers = env.readers().unwrap();
+    /// assert!(readers.iter().any(
+    ///   |r| std::process::id() == r.pid && r.txnid.is_none()));
+    /// # }
+    /// ```
+    pub fn readers(&self) -> Result<Vec<ReaderInfo>> {
+        let mut lines: Vec<String> = Vec::new();
+        unsafe {
+            let rc = ffi::mdb_reader_list(
+                self.env.0, collect_reader_line,
+                &mut lines as *mut Vec<String> as *const c_void);
+            // `mdb_reader_list()` returns the result of the last callback,
+            // or a negative error code
+            if rc < 0 {
+                return Err(Error::from_code(rc));
+            }
+        }
+
+        Ok(lines.iter().flat_map(|s| s.lines()).filter_map(parse_reader)
+           .collect())
+    }
+
+    /// Returns the read transactions currently open on this environment,
+    /// oldest first.
+    ///
+    /// This only covers transactions of this `Environment`, and requires
+    /// tracking to be enabled with `EnvBuilder::set_reader_tracking()`;
+    /// otherwise, the result is always empty.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// extern crate lmdb_zero as lmdb;
+    /// extern crate tempdir;
+    ///
+    /// # fn main() {
+    /// # let tmp = tempdir::TempDir::new_in(".", "lmdbzero").unwrap();
+    /// # let path = tmp.path().to_str().unwrap();
+    /// let mut builder = lmdb::EnvBuilder::new().unwrap();
+    /// builder.set_reader_tracking(false);
+    /// let env = unsafe {
+    ///   builder.open(path, lmdb::open::Flags::empty(), 0o600).unwrap()
+    /// };
+    ///
+    /// let txn = lmdb::ReadTransaction::new(&env).unwrap();
+    /// txn.set_label("first");
+    /// let readers = env.tracked_readers();
+    /// assert_eq!(1, readers.len());
+    /// assert_eq!(txn.id(), readers[0].id);
+    /// assert_eq!(Some("first".to_owned()), readers[0].label);
+    ///
+    /// // Reset transactions hold no snapshot, so are not tracked
+    /// let reset = txn.reset();
+    /// assert!(env.tracked_readers().is_empty());
+    /// let _txn = reset.renew().unwrap();
+    /// assert_eq!(1, env.tracked_readers().len());
+    /// # }
+    /// ```
+    pub fn tracked_readers(&self) -> Vec<TrackedReader> {
+        self.old_readers(Duration::from_secs(0))
+    }
+
+    /// Returns the read transactions of this environment which have been
+    /// open for at least `threshold`, oldest first.
+    ///
+    /// Like `tracked_readers()`, this is always empty unless tracking is
+    /// enabled with `EnvBuilder::set_reader_tracking()`.
+    pub fn old_readers(&self, threshold: Duration) -> Vec<TrackedReader> {
+        self.reader_tracker.as_ref()
+            .map_or_else(Vec::new, |tracker| tracker.readers(threshold))
+    }
+}
+
+// Returns every key in the unnamed database.
+//
+// This uses the raw cursor functions rather than `Cursor`, whose errors go
+// through `dbi_error_context()`.
+fn main_db_keys(txn: &ReadTransaction) -> Result<Vec<Vec<u8>>> {
+    let mut keys = Vec::new();
+    unsafe {
+        let mut raw: *mut ffi::MDB_cursor = ptr::null_mut();
+        lmdb_call!(ffi::mdb_cursor_open(tx::txn_ptr(txn), MAIN_DBI, &mut raw));
+        // Closes the cursor on return
+        let _cursor = cursor::create_cursor(raw, txn);
+
+        let mut key = EMPTY_VAL;
+        let mut val = EMPTY_VAL;
+        let mut op = ffi::MDB_cursor_op::MDB_FIRST;
+        loop {
+            match ffi::mdb_cursor_get(raw, &mut key, &mut val, op) {
+                0 => keys.push(mdb_val_as_bytes(txn, &key).to_vec()),
+                error::NOTFOUND => break,
+                code => return Err(Error::from_code(code)),
+            }
+            op = ffi::MDB_cursor_op::MDB_NEXT;
+        }
+    }
+    Ok(keys)
 }
 
 // Internal API
@@ -575,6 +1580,7 @@ pub fn dbi_close(this: &Environment, dbi: ffi::MDB_dbi) {
         .expect("open_dbis lock poisoned");
     assert!(locked_dbis.remove(&dbi),
             "closed dbi that wasn't open");
+    set_dbi_name(this, dbi, None);
 
     unsafe {
         ffi::mdb_dbi_close(this.env.0, dbi);
@@ -587,6 +1593,7 @@ pub fn dbi_delete(this: &Environment, dbi: ffi::MDB_dbi) -> Result<()> {
     // unsynchronised DBI table.
     let mut locked_dbis = this.open_dbis.lock()
         .expect("open_dbis lock poisoned");
+    let _active = ActiveTxn::new(this);
     unsafe {
         let mut raw_txn: *mut ffi::MDB_txn = ptr::null_mut();
         lmdb_call!(ffi::mdb_txn_begin(
@@ -597,15 +1604,141 @@ pub fn dbi_delete(this: &Environment, dbi: ffi::MDB_dbi) -> Result<()> {
     }
     assert!(locked_dbis.remove(&dbi),
             "closed dbi that wasn't open");
+    set_dbi_name(this, dbi, None);
     Ok(())
 }
 
+// Internal API
+//
+// Registers a new transaction as active, first waiting for any pending map
+// resize to complete. Every call must be matched by a call to `txn_end()`.
+pub fn txn_begin(this: &Environment) {
+    let active = &this.active_txns;
+    loop {
+        active.count.fetch_add(1, Ordering::SeqCst);
+        if !active.resizing.load(Ordering::SeqCst) {
+            return;
+        }
+
+        // Back off so the resize can proceed, and wait for it to finish.
+        txn_end(this);
+        let mut locked = active.lock.lock()
+            .expect("active_txns lock poisoned");
+        while active.resizing.load(Ordering::SeqCst) {
+            locked = active.cond.wait(locked)
+                .expect("active_txns lock poisoned");
+        }
+    }
+}
+
+// Internal API
+pub fn txn_end(this: &Environment) {
+    let active = &this.active_txns;
+    if 1 == active.count.fetch_sub(1, Ordering::SeqCst) &&
+        active.resizing.load(Ordering::SeqCst)
+    {
+        let _locked = active.lock.lock()
+            .expect("active_txns lock poisoned");
+        active.cond.notify_all();
+    }
+}
+
+// Waits on `cond` until notified, or returns `None` once `deadline` has
+// passed. A `deadline` of `None` waits indefinitely.
+fn wait_until<'a>(cond: &Condvar, locked: MutexGuard<'a, ()>,
+                  deadline: Option<Instant>) -> Option<MutexGuard<'a, ()>> {
+    let deadline = match deadline {
+        Some(deadline) => deadline,
+        None => return Some(cond.wait(locked)
+                            .expect("active_txns lock poisoned")),
+    };
+
+    let now = Instant::now();
+    if now >= deadline {
+        return None;
+    }
+    Some(cond.wait_timeout(locked, deadline - now)
+         .expect("active_txns lock poisoned").0)
+}
+
+// Internal API
+//
+// Counts a transaction used internally by this module or `dbi` as active for
+// as long as it is in scope.
+pub struct ActiveTxn<'a>(&'a Environment);
+
+impl<'a> ActiveTxn<'a> {
+    pub fn new(env: &'a Environment) -> Self {
+        txn_begin(env);
+        ActiveTxn(env)
+    }
+}
+
+impl<'a> Drop for ActiveTxn<'a> {
+    fn drop(&mut self) {
+        txn_end(self.0);
+    }
+}
+
 // Internal API
 pub fn env_ptr(this: &Environment) -> *mut ffi::MDB_env {
     this.env.0
 }
 
+// Internal API
+pub fn env_reader_tracker(this: &Environment) -> Option<&ReaderTracker> {
+    this.reader_tracker.as_ref()
+}
+
 // Internal API
 pub fn env_open_dbis(this: &Environment) -> &Mutex<HashSet<ffi::MDB_dbi>> {
     &this.open_dbis
 }
+
+// Internal API
+//
+// Records the name of `dbi` for `dbi_error_context()`, or forgets it if
+// `name` is `None`.
+pub fn set_dbi_name(this: &Environment, dbi: ffi::MDB_dbi,
+                    name: Option<&str>) {
+    let mut names = this.dbi_names.write().expect("dbi_names lock poisoned");
+    match name {
+        Some(name) => { names.insert(dbi, name.to_owned()); },
+        None => { names.remove(&dbi); },
+    }
+}
+
+// Internal API
+//
+// Wraps `error` from `operation` in an `ErrorContext`, if enabled with
+// `EnvBuilder::set_error_context()`.
+pub fn error_context(this: &Environment, error: Error,
+                     operation: &'static str, database: Option<&str>,
+                     key: Option<&[u8]>) -> Error {
+    if !this.error_context || Error::NotFound == error {
+        return error;
+    }
+
+    Error::Context(Box::new(ErrorContext {
+        error: error,
+        operation: operation,
+        database: database.map(str::to_owned),
+        key: key.map(|k| k[..k.len().min(CONTEXT_KEY_LEN)].to_vec()),
+        key_len: key.map_or(0, <[u8]>::len),
+    }))
+}
+
+// Internal API
+//
+// Like `error_context()`, but looks up the name of the database from `dbi`.
+pub fn dbi_error_context(this: &Environment, error: Error,
+                         operation: &'static str, dbi: ffi::MDB_dbi,
+                         key: Option<&[u8]>) -> Error {
+    if !this.error_context || Error::NotFound == error {
+        return error;
+    }
+
+    let name = this.dbi_names.read().expect("dbi_names lock poisoned")
+        .get(&dbi).cloned();
+    error_context(this, error, operation, name.as_ref().map(|s| &s[..]), key)
+}
@@ -11,6 +11,7 @@
 use std::error::Error as StdError;
 use std::ffi::{CStr, NulError};
 use std::fmt;
+use std::io;
 use std::result;
 use libc::c_int;
 
@@ -64,13 +65,66 @@ pub const BAD_VALSIZE: c_int = ffi::MDB_BAD_VALSIZE;
 pub const BAD_DBI: c_int = ffi2::MDB_BAD_DBI;
 
 /// Error type returned by LMDB.
+///
+/// Each error code documented by LMDB has its own variant; the constants in
+/// this module give the corresponding raw codes. Use `Error::from_code()` and
+/// `Error::code()` to convert between the two.
 #[derive(Clone,PartialEq,Eq,Hash)]
 pub enum Error {
-    /// A basic error code returned by LMDB.
+    /// `KEYEXIST`: key/data pair already exists
+    KeyExist,
+    /// `NOTFOUND`: key/data pair not found (EOF)
+    NotFound,
+    /// `PAGE_NOTFOUND`: Requested page not found - this usually indicates
+    /// corruption
+    PageNotFound,
+    /// `CORRUPTED`: Located page was wrong type
+    Corrupted,
+    /// `PANIC`: Update of meta page failed or environment had fatal error
+    Panic,
+    /// `VERSION_MISMATCH`: Environment version mismatch
+    VersionMismatch,
+    /// `INVALID`: File is not a valid LMDB file
+    Invalid,
+    /// `MAP_FULL`: Environment mapsize reached
+    MapFull,
+    /// `DBS_FULL`: Environment maxdbs reached
+    DbsFull,
+    /// `READERS_FULL`: Environment maxreaders reached
+    ReadersFull,
+    /// `TLS_FULL`: Too many TLS keys in use - Windows only
+    TlsFull,
+    /// `TXN_FULL`: Txn has too many dirty pages
+    TxnFull,
+    /// `CURSOR_FULL`: Cursor stack too deep - internal error
+    CursorFull,
+    /// `PAGE_FULL`: Page has not enough space - internal error
+    PageFull,
+    /// `MAP_RESIZED`: Database contents grew beyond environment mapsize
+    MapResized,
+    /// `INCOMPATIBLE`: Operation and DB incompatible, or DB type changed.
+    /// See `INCOMPATIBLE` for details.
+    Incompatible,
+    /// `BAD_RSLOT`: Invalid reuse of reader locktable slot
+    BadRslot,
+    /// `BAD_TXN`: Transaction must abort, has a child, or is invalid
+    BadTxn,
+    /// `BAD_VALSIZE`: Unsupported size of key/DB name/data, or wrong
+    /// `DUPFIXED` size
+    BadValSize,
+    /// `BAD_DBI`: The specified DBI was changed unexpectedly
+    BadDbi,
+    /// A raw platform error code (`errno`) returned by LMDB.
     ///
-    /// The code is generally expected to be a constant defined in the `errors`
-    /// module if negative, or a raw platform error code if positive.
-    Code(c_int),
+    /// `Error::io_kind()` gives the corresponding `std::io::ErrorKind`.
+    Os(c_int),
+    /// An LMDB error code not known to this crate, such as one introduced by
+    /// a newer version of LMDB.
+    Other(c_int),
+    /// An I/O error which did not originate from LMDB and has no platform
+    /// error code, as produced by converting such an `io::Error` into an
+    /// `Error`. The message of the original error is included.
+    Io(io::ErrorKind, String),
     /// A string path was given which contains a `NUL` byte.
     NulStr,
     /// An attempt was made to open a database which is already open.
@@ -83,6 +137,23 @@ pub enum Error {
     Mismatch,
     /// A value conversion was rejected. A message explaining why is included.
     ValRejected(String),
+    /// The checksum of a value read with `Checksummed` did not match its
+    /// data, indicating that the value is corrupt.
+    ChecksumMismatch,
+    /// An operation requiring an environment opened with `open::NOTLS` was
+    /// used on an environment without that flag.
+    NoTls,
+    /// The map could not be resized, because other transactions in this
+    /// process remained active for longer than the timeout set with
+    /// `EnvBuilder::set_resize_timeout()`.
+    ResizeTimeout,
+    /// Another error, along with a description of the operation which
+    /// produced it.
+    ///
+    /// This is only produced if enabled with
+    /// `EnvBuilder::set_error_context()`. Use `Error::root()` to get the
+    /// underlying error.
+    Context(Box<ErrorContext>),
     // Prevent external code from exhaustively matching on this enum.
     #[doc(hidden)]
     _NonExhaustive
@@ -91,7 +162,261 @@ pub enum Error {
 /// Result type returned for all calls that can fail.
 pub type Result<T> = result::Result<T, Error>;
 
+/// The maximum number of bytes of the key kept in an `ErrorContext`.
+pub const CONTEXT_KEY_LEN: usize = 32;
+
+/// Describes the operation which produced an error.
+///
+/// When enabled with `EnvBuilder::set_error_context()`, errors from
+/// `WriteAccessor::put()`, `del_key()` and `del_item()`, the operations on
+/// `Cursor`, and `Database::open()` are wrapped in `Error::Context` with one
+/// of these.
+///
+/// ## Example
+///
+/// ```
+/// extern crate lmdb_zero as lmdb;
+/// extern crate tempdir;
+///
+/// # fn main() {
+/// # let tmp = tempdir::TempDir::new_in(".", "lmdbzero").unwrap();
+/// # let path = tmp.path().to_str().unwrap();
+/// let mut builder = lmdb::EnvBuilder::new().unwrap();
+/// builder.set_maxdbs(2).unwrap();
+/// builder.set_error_context(true);
+/// let env = unsafe {
+///   builder.open(path, lmdb::open::Flags::empty(), 0o600).unwrap()
+/// };
+/// let db = lmdb::Database::open(
+///   &env, Some("users"), &lmdb::DatabaseOptions::new(lmdb::db::CREATE))
+///   .unwrap();
+///
+/// let txn = lmdb::WriteTransaction::new(&env).unwrap();
+/// {
+///   let mut access = txn.access();
+///   access.put(&db, "alice", "1", lmdb::put::Flags::empty()).unwrap();
+///   let err = access.put(&db, "alice", "2", lmdb::put::NOOVERWRITE)
+///     .unwrap_err();
+///
+///   assert_eq!(&lmdb::Error::KeyExist, err.root());
+///   let context = err.context().unwrap();
+///   assert_eq!("mdb_put", context.operation);
+///   assert_eq!(Some("users"), context.database.as_ref().map(|s| &s[..]));
+///   assert_eq!(Some(&b"alice"[..]), context.key.as_ref().map(|k| &k[..]));
+///   assert!(err.to_string().contains("mdb_put on database \"users\""));
+/// }
+/// # }
+/// ```
+#[derive(Clone,Debug,PartialEq,Eq,Hash)]
+pub struct ErrorContext {
+    /// The underlying error.
+    pub error: Error,
+    /// The name of the LMDB function which failed, such as `"mdb_put"`.
+    pub operation: &'static str,
+    /// The name of the database being operated on, or `None` for the unnamed
+    /// database.
+    pub database: Option<String>,
+    /// The key being operated on, if any, truncated to at most
+    /// `CONTEXT_KEY_LEN` bytes.
+    pub key: Option<Vec<u8>>,
+    /// The length of the key before truncation.
+    pub key_len: usize,
+}
+
+macro_rules! lmdb_codes {
+    ($($variant:ident = $code:ident),*) => {
+        impl Error {
+            /// Converts a raw error code returned by LMDB into an `Error`.
+            ///
+            /// Positive codes are platform error codes and result in
+            /// `Error::Os`. Negative codes not known to this crate result in
+            /// `Error::Other`.
+            ///
+            /// ## Example
+            ///
+            /// ```
+            /// use lmdb_zero::{error, Error};
+            ///
+            /// assert_eq!(Error::KeyExist, Error::from_code(error::KEYEXIST));
+            /// assert_eq!(Some(error::KEYEXIST), Error::KeyExist.code());
+            /// assert_eq!(None, Error::Mismatch.code());
+            /// ```
+            pub fn from_code(code: c_int) -> Self {
+                match code {
+                    $($code => Error::$variant,)*
+                    code if code > 0 => Error::Os(code),
+                    code => Error::Other(code),
+                }
+            }
+
+            /// Returns the raw LMDB or platform error code of this error, or
+            /// `None` if it did not come from LMDB.
+            pub fn code(&self) -> Option<c_int> {
+                match *self {
+                    $(Error::$variant => Some($code),)*
+                    Error::Os(code) | Error::Other(code) => Some(code),
+                    Error::Context(ref context) => context.error.code(),
+                    _ => None,
+                }
+            }
+
+            // Returns the name of the variant for a unit LMDB error, or
+            // `None` for any other kind of error.
+            fn code_name(&self) -> Option<&'static str> {
+                match *self {
+                    $(Error::$variant => Some(stringify!($variant)),)*
+                    _ => None,
+                }
+            }
+        }
+    }
+}
+
+lmdb_codes! {
+    KeyExist = KEYEXIST,
+    NotFound = NOTFOUND,
+    PageNotFound = PAGE_NOTFOUND,
+    Corrupted = CORRUPTED,
+    Panic = PANIC,
+    VersionMismatch = VERSION_MISMATCH,
+    Invalid = INVALID,
+    MapFull = MAP_FULL,
+    DbsFull = DBS_FULL,
+    ReadersFull = READERS_FULL,
+    TlsFull = TLS_FULL,
+    TxnFull = TXN_FULL,
+    CursorFull = CURSOR_FULL,
+    PageFull = PAGE_FULL,
+    MapResized = MAP_RESIZED,
+    Incompatible = INCOMPATIBLE,
+    BadRslot = BAD_RSLOT,
+    BadTxn = BAD_TXN,
+    BadValSize = BAD_VALSIZE,
+    BadDbi = BAD_DBI
+}
+
 impl Error {
+    /// Returns the context attached to this error by `Error::Context`, if
+    /// any.
+    pub fn context(&self) -> Option<&ErrorContext> {
+        match *self {
+            Error::Context(ref context) => Some(context),
+            _ => None,
+        }
+    }
+
+    /// Returns the underlying error, without any `Error::Context`.
+    ///
+    /// Code which needs to match on specific errors should generally match
+    /// on the result of this, so that it works whether or not context is
+    /// enabled.
+    pub fn root(&self) -> &Error {
+        match *self {
+            Error::Context(ref context) => context.error.root(),
+            ref error => error,
+        }
+    }
+
+    /// Returns whether an operation which failed with this error may succeed
+    /// if retried in a new transaction.
+    ///
+    /// This is the case for `MapResized` (after adopting the new map size)
+    /// and `ReadersFull`. This is used by `Environment::read()` and
+    /// `Environment::write()` to decide whether to retry.
+    ///
+    /// `TxnFull` is not retryable, since rerunning the same work in a new
+    /// transaction dirties just as many pages; see `is_txn_full()`.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use lmdb_zero::Error;
+    ///
+    /// assert!(Error::ReadersFull.is_retryable());
+    /// assert!(!Error::TxnFull.is_retryable());
+    /// assert!(!Error::NotFound.is_retryable());
+    /// assert!(!Error::Mismatch.is_retryable());
+    /// ```
+    pub fn is_retryable(&self) -> bool {
+        matches!(*self.root(), Error::MapResized | Error::ReadersFull)
+    }
+
+    /// Returns whether this error indicates that a write transaction has
+    /// dirtied too many pages.
+    ///
+    /// The transaction cannot go on, and retrying the same work will fail
+    /// the same way. Callers seeing this should instead split the work
+    /// across several smaller transactions.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use lmdb_zero::Error;
+    ///
+    /// assert!(Error::TxnFull.is_txn_full());
+    /// assert!(!Error::MapFull.is_txn_full());
+    /// ```
+    pub fn is_txn_full(&self) -> bool {
+        Error::TxnFull == *self.root()
+    }
+
+    /// Returns whether this error indicates that the database file or a value
+    /// stored in it is corrupt.
+    ///
+    /// This is the case for `PageNotFound`, `Corrupted`, `Invalid` and
+    /// `ChecksumMismatch`.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use lmdb_zero::Error;
+    ///
+    /// assert!(Error::Corrupted.is_corruption());
+    /// assert!(Error::ChecksumMismatch.is_corruption());
+    /// assert!(!Error::MapFull.is_corruption());
+    /// ```
+    pub fn is_corruption(&self) -> bool {
+        matches!(*self.root(), Error::PageNotFound | Error::Corrupted |
+                 Error::Invalid | Error::ChecksumMismatch)
+    }
+
+    /// Returns the `std::io::ErrorKind` best describing this error.
+    ///
+    /// Platform error codes (`Error::Os`) are mapped the same way as by
+    /// `io::Error::from_raw_os_error()`. Of the LMDB errors, `NotFound` maps
+    /// to `NotFound`, `KeyExist` to `AlreadyExists`, errors for which
+    /// `is_corruption()` is true and `ValRejected` to `InvalidData`, and
+    /// `BadValSize` and `NulStr` to `InvalidInput`, and `ResizeTimeout` to
+    /// `TimedOut`. Everything else maps to `Other`.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// extern crate libc;
+    /// extern crate lmdb_zero;
+    /// use std::io;
+    /// use lmdb_zero::Error;
+    ///
+    /// # fn main() {
+    /// assert_eq!(io::ErrorKind::NotFound, Error::NotFound.io_kind());
+    /// assert_eq!(io::ErrorKind::PermissionDenied,
+    ///            Error::Os(libc::EACCES).io_kind());
+    /// # }
+    /// ```
+    pub fn io_kind(&self) -> io::ErrorKind {
+        match *self.root() {
+            Error::Os(code) => io::Error::from_raw_os_error(code).kind(),
+            Error::Io(kind, _) => kind,
+            Error::NotFound => io::ErrorKind::NotFound,
+            Error::KeyExist => io::ErrorKind::AlreadyExists,
+            Error::BadValSize | Error::NulStr => io::ErrorKind::InvalidInput,
+            Error::ValRejected(..) => io::ErrorKind::InvalidData,
+            Error::ResizeTimeout => io::ErrorKind::TimedOut,
+            ref e if e.is_corruption() => io::ErrorKind::InvalidData,
+            _ => io::ErrorKind::Other,
+        }
+    }
+
     fn strerror(&self) -> &'static str {
         match *self {
             Error::NulStr => "NUL byte in path",
@@ -100,8 +425,15 @@ impl Error {
                 "Items from different env/database used together",
             Error::ValRejected(..) =>
                 "Value conversion failed",
+            Error::ChecksumMismatch => "Checksum mismatch",
+            Error::NoTls => "Environment not opened with NOTLS",
+            Error::ResizeTimeout =>
+                "Timed out waiting for transactions to end to resize the map",
+            Error::Io(..) => "I/O error",
+            Error::Context(ref context) => context.error.strerror(),
             Error::_NonExhaustive => "Error::_NonExhaustive",
-            Error::Code(code) => unsafe {
+            ref e => unsafe {
+                let code = e.code().expect("LMDB error without code");
                 let raw = ffi::mdb_strerror(code);
                 if raw.is_null() {
                     "(null)"
@@ -115,6 +447,10 @@ impl Error {
 
 impl fmt::Debug for Error {
     fn fmt(&self, f: &mut fmt::Formatter) -> result::Result<(), fmt::Error> {
+        if let Some(name) = self.code_name() {
+            return write!(f, "Error::{}", name);
+        }
+
         match *self {
             Error::NulStr =>
                 write!(f, "Error::NulStr"),
@@ -124,9 +460,21 @@ impl fmt::Debug for Error {
                 write!(f, "Error::Mismatch"),
             Error::ValRejected(ref why) =>
                 write!(f, "Error::ValRejected({:?})", why),
-            Error::Code(code) =>
-                write!(f, "Error::Code({}, '{}')", code, self.strerror()),
-            Error::_NonExhaustive =>
+            Error::ChecksumMismatch =>
+                write!(f, "Error::ChecksumMismatch"),
+            Error::NoTls =>
+                write!(f, "Error::NoTls"),
+            Error::ResizeTimeout =>
+                write!(f, "Error::ResizeTimeout"),
+            Error::Os(code) =>
+                write!(f, "Error::Os({}, '{}')", code, self.strerror()),
+            Error::Other(code) =>
+                write!(f, "Error::Other({}, '{}')", code, self.strerror()),
+            Error::Io(kind, ref why) =>
+                write!(f, "Error::Io({:?}, {:?})", kind, why),
+            Error::Context(ref context) =>
+                write!(f, "Error::Context({:?})", context),
+            _ =>
                 write!(f, "Error::_NonExhaustive"),
         }
     }
@@ -137,6 +485,23 @@ impl fmt::Display for Error {
         match *self {
             Error::ValRejected(ref why) =>
                 write!(f, "Value conversion failed: {}", why),
+            Error::Io(_, ref why) =>
+                write!(f, "I/O error: {}", why),
+            Error::Context(ref context) => {
+                try!(write!(f, "{} ({}", context.error, context.operation));
+                match context.database {
+                    Some(ref name) =>
+                        try!(write!(f, " on database {:?}", name)),
+                    None => try!(write!(f, " on unnamed database")),
+                }
+                if let Some(ref key) = context.key {
+                    try!(write!(f, ", key b\"{}\"", key.escape_ascii()));
+                    if context.key_len > key.len() {
+                        try!(write!(f, "... ({} bytes)", context.key_len));
+                    }
+                }
+                write!(f, ")")
+            },
             _ => write!(f, "{}", self.strerror()),
         }
     }
@@ -146,6 +511,13 @@ impl StdError for Error {
     fn description(&self) -> &str {
         self.strerror()
     }
+
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        match *self {
+            Error::Context(ref context) => Some(&context.error),
+            _ => None,
+        }
+    }
 }
 
 impl From<NulError> for Error {
@@ -154,6 +526,54 @@ impl From<NulError> for Error {
     }
 }
 
+/// Converts an `Error` into an `io::Error`.
+///
+/// `Error::Os` becomes an `io::Error` with the same platform error code, and
+/// `Error::Io` an `io::Error` with the same kind and message. Any other error
+/// is wrapped, with the kind given by `Error::io_kind()`, and can be recovered
+/// by converting the `io::Error` back into an `Error`.
+///
+/// ## Example
+///
+/// ```
+/// use std::io;
+/// use lmdb_zero::Error;
+///
+/// let err: io::Error = Error::KeyExist.into();
+/// assert_eq!(io::ErrorKind::AlreadyExists, err.kind());
+/// assert_eq!(Error::KeyExist, Error::from(err));
+/// ```
+impl From<Error> for io::Error {
+    fn from(err: Error) -> Self {
+        match err {
+            Error::Os(code) => io::Error::from_raw_os_error(code),
+            Error::Io(kind, why) => io::Error::new(kind, why),
+            err => io::Error::new(err.io_kind(), err),
+        }
+    }
+}
+
+/// Converts an `io::Error` into an `Error`.
+///
+/// An `io::Error` wrapping an `Error` gives back the original error. Errors
+/// with a platform error code become `Error::Os`, and anything else
+/// `Error::Io`.
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        if let Some(code) = err.raw_os_error() {
+            return Error::Os(code);
+        }
+
+        if let Some(inner) = err.get_ref().and_then(
+            |inner| inner.downcast_ref::<Error>())
+        {
+            return inner.clone();
+        }
+
+        Error::Io(err.kind(), err.to_string())
+    }
+}
+
 /// Extension methods for LMDB results
 pub trait LmdbResultExt {
     #[allow(missing_docs)]
@@ -162,13 +582,14 @@ pub trait LmdbResultExt {
     /// Lift "not found" errors to `None`.
     ///
     /// If `Ok(val)`, return `Ok(Some(val))`. If `Err` but the error is
-    /// `Error::Code(NOTFOUND)`, return `Ok(None)`. Otherwise, return self.
+    /// `Error::NotFound` (possibly with context), return `Ok(None)`.
+    /// Otherwise, return self.
     fn to_opt(self) -> Result<Option<Self::Inner>>;
 
     /// Suppress `KEYEXIST` errors.
     ///
-    /// If this is `Err` and the error is `Error::Code(KEYEXIST)`, switch to
-    /// `Ok` with the given inner value.
+    /// If this is `Err` and the error is `Error::KeyExist` (possibly with
+    /// context), switch to `Ok` with the given inner value.
     fn ignore_exists(self, inner: Self::Inner) -> Self;
 }
 
@@ -178,7 +599,7 @@ impl<T> LmdbResultExt for Result<T> {
     fn to_opt(self) -> Result<Option<T>> {
         match self {
             Ok(val) => Ok(Some(val)),
-            Err(Error::Code(code)) if NOTFOUND == code => Ok(None),
+            Err(ref error) if Error::NotFound == *error.root() => Ok(None),
             Err(error) => Err(error),
         }
     }
@@ -186,7 +607,7 @@ impl<T> LmdbResultExt for Result<T> {
     fn ignore_exists(self, inner: T) -> Self {
         match self {
             Ok(val) => Ok(val),
-            Err(Error::Code(code)) if KEYEXIST == code => Ok(inner),
+            Err(ref error) if Error::KeyExist == *error.root() => Ok(inner),
             Err(error) => Err(error),
         }
     }
@@ -22,4 +22,6 @@ extern "C" {
     pub fn mdb_env_copyfd2(env: *mut ffi::MDB_env, fd: ffi::mdb_filehandle_t,
                            flags: c_uint) -> c_int;
     pub fn mdb_txn_id(txn: *mut ffi::MDB_txn) -> size_t;
+    // liblmdb-sys declares this as returning a pointer
+    pub fn mdb_cursor_dbi(cursor: *mut ffi::MDB_cursor) -> ffi::MDB_dbi;
 }
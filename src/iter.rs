@@ -6,11 +6,15 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
 
+use std::borrow::{Borrow, Cow};
+use std::cmp::Ordering;
 use std::iter::Iterator;
-use std::ops::{Deref, DerefMut};
+use std::marker::PhantomData;
+use std::ops::{Bound, Deref, DerefMut, RangeBounds};
 
-use cursor::Cursor;
-use error::Result;
+use cursor::{self, Cursor};
+use dbi::db;
+use error::{Error, Result};
 use tx::ConstAccessor;
 use traits::*;
 
@@ -138,3 +142,834 @@ for CursorIter<'a, 'access, 'txn, 'db, T> {
         }
     }
 }
+
+/// An iterator over the key/value pairs of a database whose keys fall within
+/// a range.
+///
+/// Keys are compared using the database's own ordering, so ranges behave as
+/// expected on databases using `INTEGERKEY`, `REVERSEKEY` or a comparison
+/// function configured with `DatabaseOptions::sort_keys_as`.
+///
+/// On `DUPSORT` databases, every value of each key in the range is produced.
+///
+/// LMDB does not allow empty keys, so an empty bound is taken to sort before
+/// every key. An empty start bound is thus the same as no start bound, while
+/// an empty end bound produces nothing.
+///
+/// Once the iterator has produced an error, it produces nothing further.
+///
+/// This is normally created with `ConstTransaction::range`.
+///
+/// ## Double-ended iteration
+///
+/// `RangeIter` implements `DoubleEndedIterator`, so ranges can be walked in
+/// reverse with `.rev()` or from both ends at once. Walking from the back
+/// uses a second cursor, which is only opened the first time `next_back()`
+/// is called. The two ends stop when they meet, so no item is produced
+/// twice.
+///
+/// ```
+/// # include!("src/example_helpers.rs");
+/// # fn main() {
+/// # let env = create_env();
+/// # let db = dupdb(&env);
+/// let txn = lmdb::WriteTransaction::new(&env).unwrap();
+/// {
+///   let mut access = txn.access();
+///   let f = lmdb::put::Flags::empty();
+///   access.put(&db, "Fruit", "Apple", f).unwrap();
+///   access.put(&db, "Fruit", "Orange", f).unwrap();
+///   access.put(&db, "Animal", "Badger", f).unwrap();
+///   access.put(&db, "Veggie", "Carrot", f).unwrap();
+///
+///   let mut iter = txn.range::<str, str, str, _>(&db, &access, ..).unwrap();
+///   assert_eq!(("Veggie", "Carrot"), iter.next_back().unwrap().unwrap());
+///   assert_eq!(("Animal", "Badger"), iter.next().unwrap().unwrap());
+///   assert_eq!(("Fruit", "Orange"), iter.next_back().unwrap().unwrap());
+///   assert_eq!(("Fruit", "Apple"), iter.next().unwrap().unwrap());
+///   assert!(iter.next().is_none());
+///   assert!(iter.next_back().is_none());
+/// }
+/// txn.commit().unwrap();
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct RangeIter<'access, 'txn: 'access, 'db: 'txn,
+                     K : ?Sized + 'access, V : ?Sized + 'access> {
+    front: Cursor<'txn,'db>,
+    back: Option<Cursor<'txn,'db>>,
+    access: &'access ConstAccessor<'txn>,
+    start: Bound<Vec<u8>>,
+    end: Bound<Vec<u8>>,
+    last_front: Option<(&'access K, &'access V)>,
+    last_back: Option<(&'access K, &'access V)>,
+    exhausted: bool,
+}
+
+// Returns whether `bound` is an empty key. No key can sort before it, since
+// LMDB does not allow empty keys.
+fn is_empty_bound<K : AsLmdbBytes + ?Sized, B : Borrow<K> + ?Sized>
+    (bound: Bound<&B>) -> bool
+{
+    match bound {
+        Bound::Unbounded => false,
+        Bound::Included(b) | Bound::Excluded(b) =>
+            b.borrow().as_lmdb_bytes().is_empty(),
+    }
+}
+
+fn bound_to_vec<K : AsLmdbBytes + ?Sized, B : Borrow<K> + ?Sized>
+    (bound: Bound<&B>) -> Bound<Vec<u8>>
+{
+    match bound {
+        Bound::Unbounded => Bound::Unbounded,
+        Bound::Included(b) =>
+            Bound::Included(b.borrow().as_lmdb_bytes().to_vec()),
+        Bound::Excluded(b) =>
+            Bound::Excluded(b.borrow().as_lmdb_bytes().to_vec()),
+    }
+}
+
+impl<'access, 'txn: 'access, 'db: 'txn,
+     K : AsLmdbBytes + FromLmdbBytes + ?Sized + 'access,
+     V : AsLmdbBytes + FromLmdbBytes + ?Sized + 'access>
+RangeIter<'access, 'txn, 'db, K, V> {
+    /// Creates a range iterator from the given cursor and accessor.
+    ///
+    /// The cursor is positioned at the first item in `range` when the first
+    /// item is requested; any prior position is lost. The bounds of `range`
+    /// may be of any type which can be borrowed as `K`, so that, for
+    /// example, `"a".."m"` can be used with `str` keys.
+    ///
+    /// The bounds are copied into the iterator, so `range` need not outlive
+    /// it.
+    pub fn new<B : Borrow<K> + ?Sized, R : RangeBounds<B>>
+        (cursor: Cursor<'txn,'db>,
+         access: &'access ConstAccessor<'txn>,
+         range: R) -> Self
+    {
+        // An empty start bound does not restrict the range, while nothing
+        // comes before an empty end bound. Neither can be passed to
+        // `seek_range_k`.
+        let start = if is_empty_bound::<K,B>(range.start_bound()) {
+            Bound::Unbounded
+        } else {
+            bound_to_vec::<K,B>(range.start_bound())
+        };
+        RangeIter {
+            front: cursor,
+            back: None,
+            access: access,
+            start: start,
+            end: bound_to_vec::<K,B>(range.end_bound()),
+            last_front: None,
+            last_back: None,
+            exhausted: is_empty_bound::<K,B>(range.end_bound()),
+        }
+    }
+
+    /// Positions the front cursor at the first item in the range, returning
+    /// that item.
+    fn front_head(&mut self) -> Result<Option<(&'access K, &'access V)>> {
+        let (front, access) = (&mut self.front, self.access);
+        match self.start {
+            Bound::Unbounded => front.first(access).to_opt(),
+            Bound::Included(ref start) => {
+                match try!(front.seek_range_k::<[u8],[u8]>(
+                    access, &start[..]).to_opt())
+                {
+                    Some(_) => front.get_current(access).to_opt(),
+                    None => Ok(None),
+                }
+            },
+            Bound::Excluded(ref start) => {
+                match try!(front.seek_range_k::<[u8],[u8]>(
+                    access, &start[..]).to_opt())
+                {
+                    Some((k, _)) if Ordering::Equal ==
+                        cursor::cmp_keys(front, k, start) =>
+                        front.next_nodup(access).to_opt(),
+                    Some(_) => front.get_current(access).to_opt(),
+                    None => Ok(None),
+                }
+            },
+        }
+    }
+
+    /// Positions the back cursor at the last item in the range, returning
+    /// that item.
+    fn back_head(&self, back: &mut Cursor<'txn,'db>)
+                 -> Result<Option<(&'access K, &'access V)>> {
+        let access = self.access;
+        match self.end {
+            Bound::Unbounded => back.last(access).to_opt(),
+            Bound::Included(ref end) => {
+                match try!(back.seek_range_k::<[u8],[u8]>(
+                    access, &end[..]).to_opt())
+                {
+                    Some((k, _)) if Ordering::Equal ==
+                        cursor::cmp_keys(back, k, end) =>
+                    {
+                        if cursor::db_flags(back).contains(db::DUPSORT) {
+                            try!(back.last_dup::<[u8]>(access));
+                        }
+                        back.get_current(access).to_opt()
+                    },
+                    Some(_) => back.prev(access).to_opt(),
+                    None => back.last(access).to_opt(),
+                }
+            },
+            Bound::Excluded(ref end) => {
+                match try!(back.seek_range_k::<[u8],[u8]>(
+                    access, &end[..]).to_opt())
+                {
+                    Some(_) => back.prev(access).to_opt(),
+                    None => back.last(access).to_opt(),
+                }
+            },
+        }
+    }
+
+    fn after_start(&self, key: &K) -> bool {
+        let cmp = |start: &[u8]| cursor::cmp_keys(
+            &self.front, key.as_lmdb_bytes(), start);
+        match self.start {
+            Bound::Unbounded => true,
+            Bound::Included(ref start) => Ordering::Less != cmp(start),
+            Bound::Excluded(ref start) => Ordering::Greater == cmp(start),
+        }
+    }
+
+    fn before_end(&self, key: &K) -> bool {
+        let cmp = |end: &[u8]| cursor::cmp_keys(
+            &self.front, key.as_lmdb_bytes(), end);
+        match self.end {
+            Bound::Unbounded => true,
+            Bound::Included(ref end) => Ordering::Greater != cmp(end),
+            Bound::Excluded(ref end) => Ordering::Less == cmp(end),
+        }
+    }
+
+    fn cmp_items(&self, a: (&K, &V), b: (&K, &V)) -> Ordering {
+        cursor::cmp_items(&self.front,
+                          (a.0.as_lmdb_bytes(), a.1.as_lmdb_bytes()),
+                          (b.0.as_lmdb_bytes(), b.1.as_lmdb_bytes()))
+    }
+}
+
+impl<'access, 'txn: 'access, 'db: 'txn,
+     K : AsLmdbBytes + FromLmdbBytes + ?Sized + 'access,
+     V : AsLmdbBytes + FromLmdbBytes + ?Sized + 'access> Iterator
+for RangeIter<'access, 'txn, 'db, K, V> {
+    type Item = Result<(&'access K, &'access V)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.exhausted {
+            return None;
+        }
+
+        let item = if self.last_front.is_some() {
+            self.front.next(self.access).to_opt()
+        } else {
+            self.front_head()
+        };
+        let item = match item {
+            Ok(Some(item)) => item,
+            Ok(None) => {
+                self.exhausted = true;
+                return None;
+            },
+            Err(err) => {
+                self.exhausted = true;
+                return Some(Err(err));
+            },
+        };
+
+        let met_back = match self.last_back {
+            Some(back) => Ordering::Less != self.cmp_items(item, back),
+            None => false,
+        };
+        if met_back || !self.before_end(item.0) {
+            self.exhausted = true;
+            None
+        } else {
+            self.last_front = Some(item);
+            Some(Ok(item))
+        }
+    }
+}
+
+impl<'access, 'txn: 'access, 'db: 'txn,
+     K : AsLmdbBytes + FromLmdbBytes + ?Sized + 'access,
+     V : AsLmdbBytes + FromLmdbBytes + ?Sized + 'access> DoubleEndedIterator
+for RangeIter<'access, 'txn, 'db, K, V> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.exhausted {
+            return None;
+        }
+
+        let mut back = match self.back.take() {
+            Some(back) => back,
+            None => match cursor::reopen(&self.front) {
+                Ok(back) => back,
+                Err(err) => {
+                    self.exhausted = true;
+                    return Some(Err(err));
+                },
+            },
+        };
+        let item = if self.last_back.is_some() {
+            back.prev(self.access).to_opt()
+        } else {
+            self.back_head(&mut back)
+        };
+        self.back = Some(back);
+        let item = match item {
+            Ok(Some(item)) => item,
+            Ok(None) => {
+                self.exhausted = true;
+                return None;
+            },
+            Err(err) => {
+                self.exhausted = true;
+                return Some(Err(err));
+            },
+        };
+
+        let met_front = match self.last_front {
+            Some(front) => Ordering::Greater != self.cmp_items(item, front),
+            None => false,
+        };
+        if met_front || !self.after_start(item.0) {
+            self.exhausted = true;
+            None
+        } else {
+            self.last_back = Some(item);
+            Some(Ok(item))
+        }
+    }
+}
+
+/// Walks the keys of a database which begin with a byte prefix.
+#[derive(Debug)]
+struct PrefixCursor<'access, 'txn: 'access, 'db: 'txn> {
+    cursor: Cursor<'txn,'db>,
+    access: &'access ConstAccessor<'txn>,
+    prefix: Vec<u8>,
+    started: bool,
+    exhausted: bool,
+}
+
+impl<'access, 'txn: 'access, 'db: 'txn> PrefixCursor<'access, 'txn, 'db> {
+    fn new(cursor: Cursor<'txn,'db>, access: &'access ConstAccessor<'txn>,
+           prefix: &[u8]) -> Self {
+        PrefixCursor {
+            cursor: cursor,
+            access: access,
+            prefix: prefix.to_vec(),
+            started: false,
+            exhausted: false,
+        }
+    }
+
+    fn advance(&mut self, nodup: bool)
+               -> Option<Result<(&'access [u8], &'access [u8])>> {
+        if self.exhausted {
+            return None;
+        }
+
+        let item = if self.started {
+            if nodup {
+                self.cursor.next_nodup(self.access)
+            } else {
+                self.cursor.next(self.access)
+            }
+        } else if self.prefix.is_empty() {
+            // Every key begins with the empty prefix, which cannot be passed
+            // to `seek_range_k`
+            self.started = true;
+            self.cursor.first(self.access)
+        } else {
+            self.started = true;
+            self.cursor.seek_range_k(self.access, &self.prefix[..])
+        };
+
+        match item.to_opt() {
+            Ok(Some(item)) if item.0.starts_with(&self.prefix) =>
+                Some(Ok(item)),
+            Ok(_) => {
+                self.exhausted = true;
+                None
+            },
+            Err(err) => {
+                self.exhausted = true;
+                Some(Err(err))
+            },
+        }
+    }
+}
+
+/// An iterator over the key/value pairs of a database whose keys begin with
+/// a given prefix.
+///
+/// The prefix is matched against the raw bytes of each key. Iteration begins
+/// at the first key greater than or equal to the prefix (as with
+/// `Cursor::seek_range_k`) and stops at the first key not beginning with it,
+/// so this is only meaningful for databases whose keys are ordered
+/// byte-wise, which is the default. An empty prefix produces every item.
+///
+/// On `DUPSORT` databases, every value of each key is produced.
+///
+/// Once the iterator has produced an error, it produces nothing further.
+///
+/// This is normally created with `ConstTransaction::prefix_iter`.
+#[derive(Debug)]
+pub struct PrefixIter<'access, 'txn: 'access, 'db: 'txn,
+                      K : ?Sized, V : ?Sized> {
+    inner: PrefixCursor<'access, 'txn, 'db>,
+    _kv: PhantomData<fn () -> (&'access K, &'access V)>,
+}
+
+impl<'access, 'txn: 'access, 'db: 'txn, K : ?Sized, V : ?Sized>
+PrefixIter<'access, 'txn, 'db, K, V> {
+    /// Creates a prefix iterator from the given cursor and accessor.
+    ///
+    /// The cursor is positioned when the first item is requested; any prior
+    /// position is lost. `prefix` is copied into the iterator.
+    pub fn new<P : AsLmdbBytes + ?Sized>
+        (cursor: Cursor<'txn,'db>, access: &'access ConstAccessor<'txn>,
+         prefix: &P) -> Self
+    {
+        PrefixIter {
+            inner: PrefixCursor::new(cursor, access, prefix.as_lmdb_bytes()),
+            _kv: PhantomData,
+        }
+    }
+}
+
+impl<'access, 'txn: 'access, 'db: 'txn,
+     K : FromLmdbBytes + ?Sized + 'access,
+     V : FromLmdbBytes + ?Sized + 'access> Iterator
+for PrefixIter<'access, 'txn, 'db, K, V> {
+    type Item = Result<(&'access K, &'access V)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.advance(false).map(|item| item.and_then(|(k, v)| Ok((
+            try!(K::from_lmdb_bytes(k).map_err(Error::ValRejected)),
+            try!(V::from_lmdb_bytes(v).map_err(Error::ValRejected))))))
+    }
+}
+
+/// An iterator over the distinct keys of a database which begin with a given
+/// prefix.
+///
+/// This is like `PrefixIter`, but only produces keys. Values are never
+/// converted, and on `DUPSORT` databases each key is produced once.
+///
+/// This is normally created with `ConstTransaction::prefix_keys`.
+#[derive(Debug)]
+pub struct PrefixKeys<'access, 'txn: 'access, 'db: 'txn, K : ?Sized> {
+    inner: PrefixCursor<'access, 'txn, 'db>,
+    _k: PhantomData<fn () -> &'access K>,
+}
+
+impl<'access, 'txn: 'access, 'db: 'txn, K : ?Sized>
+PrefixKeys<'access, 'txn, 'db, K> {
+    /// Creates a prefix key iterator from the given cursor and accessor.
+    ///
+    /// The cursor is positioned when the first item is requested; any prior
+    /// position is lost. `prefix` is copied into the iterator.
+    pub fn new<P : AsLmdbBytes + ?Sized>
+        (cursor: Cursor<'txn,'db>, access: &'access ConstAccessor<'txn>,
+         prefix: &P) -> Self
+    {
+        PrefixKeys {
+            inner: PrefixCursor::new(cursor, access, prefix.as_lmdb_bytes()),
+            _k: PhantomData,
+        }
+    }
+}
+
+impl<'access, 'txn: 'access, 'db: 'txn,
+     K : FromLmdbBytes + ?Sized + 'access> Iterator
+for PrefixKeys<'access, 'txn, 'db, K> {
+    type Item = Result<&'access K>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.advance(true).map(|item| item.and_then(
+            |(k, _)| K::from_lmdb_bytes(k).map_err(Error::ValRejected)))
+    }
+}
+
+#[derive(Debug)]
+enum DupPos<'access> {
+    Unpositioned,
+    Single,
+    Page { page: &'access [u8], size: usize, offset: usize },
+    Done,
+}
+
+/// An iterator over the values of a single key in a `DUPSORT` database.
+///
+/// Values are produced in their sorted order. On databases which are also
+/// `DUPFIXED`, values are read a page at a time with
+/// `Cursor::get_multiple` and `Cursor::next_multiple` rather than one at a
+/// time.
+///
+/// On databases without `DUPSORT`, this simply produces the single value of
+/// the key, if any.
+///
+/// This is normally created with `ConstTransaction::iter_dup_of` or by
+/// `DupGroups`.
+#[derive(Debug)]
+pub struct DupIter<'access, 'txn: 'access, 'db: 'txn, V : ?Sized> {
+    cursor: Cursor<'txn,'db>,
+    access: &'access ConstAccessor<'txn>,
+    key: Cow<'access, [u8]>,
+    pos: DupPos<'access>,
+    _v: PhantomData<fn () -> &'access V>,
+}
+
+impl<'access, 'txn: 'access, 'db: 'txn, V : ?Sized>
+DupIter<'access, 'txn, 'db, V> {
+    /// Creates an iterator over the values of `key` from the given cursor and
+    /// accessor.
+    ///
+    /// The cursor is positioned when the first item is requested; any prior
+    /// position is lost. `key` is copied into the iterator.
+    pub fn new<K : AsLmdbBytes + ?Sized>
+        (cursor: Cursor<'txn,'db>, access: &'access ConstAccessor<'txn>,
+         key: &K) -> Self
+    {
+        DupIter::at(cursor, access, Cow::Owned(key.as_lmdb_bytes().to_vec()))
+    }
+
+    fn at(cursor: Cursor<'txn,'db>, access: &'access ConstAccessor<'txn>,
+          key: Cow<'access, [u8]>) -> Self {
+        DupIter {
+            cursor: cursor,
+            access: access,
+            key: key,
+            pos: DupPos::Unpositioned,
+            _v: PhantomData,
+        }
+    }
+
+    fn next_bytes(&mut self) -> Result<Option<&'access [u8]>> {
+        let access = self.access;
+        loop {
+            match self.pos {
+                DupPos::Unpositioned => {
+                    let first = match try!(self.cursor.seek_k::<[u8],[u8]>(
+                        access, &self.key).to_opt())
+                    {
+                        Some(first) => first,
+                        None => {
+                            self.pos = DupPos::Done;
+                            return Ok(None);
+                        },
+                    };
+
+                    // GET_MULTIPLE produces nothing for a key with only one
+                    // value, since it has no sub-database.
+                    if !first.is_empty() && cursor::db_flags(&self.cursor)
+                        .contains(db::DUPSORT | db::DUPFIXED) &&
+                        try!(self.cursor.count()) > 1
+                    {
+                        let page = try!(self.cursor.get_multiple(access));
+                        self.pos = DupPos::Page {
+                            page: page, size: first.len(), offset: 0,
+                        };
+                    } else {
+                        self.pos = DupPos::Single;
+                        return Ok(Some(first));
+                    }
+                },
+
+                DupPos::Single => {
+                    let next = try!(self.cursor.next_dup::<[u8],[u8]>(
+                        access).to_opt());
+                    if next.is_none() {
+                        self.pos = DupPos::Done;
+                    }
+                    return Ok(next.map(|(_, v)| v));
+                },
+
+                DupPos::Page { page, size, ref mut offset }
+                if *offset < page.len() => {
+                    let item = &page[*offset..*offset + size];
+                    *offset += size;
+                    return Ok(Some(item));
+                },
+
+                DupPos::Page { size, .. } => {
+                    match try!(self.cursor.next_multiple(access).to_opt()) {
+                        Some(page) => self.pos = DupPos::Page {
+                            page: page, size: size, offset: 0,
+                        },
+                        None => self.pos = DupPos::Done,
+                    }
+                },
+
+                DupPos::Done => return Ok(None),
+            }
+        }
+    }
+}
+
+impl<'access, 'txn: 'access, 'db: 'txn, V : FromLmdbBytes + ?Sized + 'access>
+Iterator for DupIter<'access, 'txn, 'db, V> {
+    type Item = Result<&'access V>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.next_bytes() {
+            Ok(Some(bytes)) => Some(
+                V::from_lmdb_bytes(bytes).map_err(Error::ValRejected)),
+            Ok(None) => None,
+            Err(err) => Some(Err(err)),
+        }
+    }
+}
+
+/// An iterator over the distinct keys of a `DUPSORT` database, producing
+/// each key along with a `DupIter` over its values.
+///
+/// Each `DupIter` uses its own cursor, so it remains usable after the
+/// iterator has moved on to later keys. The `DupIter` does not touch the
+/// database until it is first advanced, so skipping the values of a key
+/// costs only the opening of the cursor.
+///
+/// This is normally created with `ConstTransaction::iter_dup_groups`.
+#[derive(Debug)]
+pub struct DupGroups<'access, 'txn: 'access, 'db: 'txn,
+                     K : ?Sized, V : ?Sized> {
+    cursor: Cursor<'txn,'db>,
+    access: &'access ConstAccessor<'txn>,
+    started: bool,
+    _kv: PhantomData<fn () -> (&'access K, &'access V)>,
+}
+
+impl<'access, 'txn: 'access, 'db: 'txn, K : ?Sized, V : ?Sized>
+DupGroups<'access, 'txn, 'db, K, V> {
+    /// Creates an iterator over the key groups of the database of the given
+    /// cursor.
+    ///
+    /// The cursor is positioned at the first key when the first item is
+    /// requested; any prior position is lost.
+    pub fn new(cursor: Cursor<'txn,'db>,
+               access: &'access ConstAccessor<'txn>) -> Self {
+        DupGroups {
+            cursor: cursor,
+            access: access,
+            started: false,
+            _kv: PhantomData,
+        }
+    }
+}
+
+impl<'access, 'txn: 'access, 'db: 'txn,
+     K : FromLmdbBytes + ?Sized + 'access, V : ?Sized> Iterator
+for DupGroups<'access, 'txn, 'db, K, V> {
+    type Item = Result<(&'access K, DupIter<'access, 'txn, 'db, V>)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let item = if self.started {
+            self.cursor.next_nodup::<[u8],[u8]>(self.access)
+        } else {
+            self.started = true;
+            self.cursor.first::<[u8],[u8]>(self.access)
+        };
+
+        match item.to_opt() {
+            Ok(Some((k, _))) => Some(
+                K::from_lmdb_bytes(k).map_err(Error::ValRejected)
+                    .and_then(|key| {
+                        let values = DupIter::at(
+                            try!(cursor::reopen(&self.cursor)),
+                            self.access, Cow::Borrowed(k));
+                        Ok((key, values))
+                    })),
+            Ok(None) => None,
+            Err(err) => Some(Err(err)),
+        }
+    }
+}
+
+#[derive(Debug)]
+enum PagePos<'access> {
+    Unpositioned,
+    InKey(&'access [u8]),
+    Done,
+}
+
+/// Walks the pages of duplicate values of one or all keys of a `DUPFIXED`
+/// database.
+#[derive(Debug)]
+struct PageCursor<'access, 'txn: 'access, 'db: 'txn> {
+    cursor: Cursor<'txn,'db>,
+    access: &'access ConstAccessor<'txn>,
+    key: Option<Vec<u8>>,
+    pos: PagePos<'access>,
+}
+
+impl<'access, 'txn: 'access, 'db: 'txn> PageCursor<'access, 'txn, 'db> {
+    fn new(cursor: Cursor<'txn,'db>, access: &'access ConstAccessor<'txn>,
+           key: Option<Vec<u8>>) -> Self {
+        PageCursor {
+            cursor: cursor,
+            access: access,
+            key: key,
+            pos: PagePos::Unpositioned,
+        }
+    }
+
+    fn next_page(&mut self)
+                 -> Result<Option<(&'access [u8], &'access [u8])>> {
+        let access = self.access;
+        let first = match self.pos {
+            PagePos::Done => return Ok(None),
+
+            PagePos::Unpositioned => {
+                if !cursor::db_flags(&self.cursor)
+                    .contains(db::DUPSORT | db::DUPFIXED)
+                {
+                    return Err(Error::Incompatible);
+                }
+
+                match self.key {
+                    Some(ref key) => self.cursor.seek_k_both::<[u8],[u8]>(
+                        access, &key[..]),
+                    None => self.cursor.first(access),
+                }
+            },
+
+            PagePos::InKey(key) => {
+                match try!(self.cursor.next_multiple(access).to_opt()) {
+                    Some(page) => return Ok(Some((key, page))),
+                    None if self.key.is_some() => {
+                        self.pos = PagePos::Done;
+                        return Ok(None);
+                    },
+                    None => self.cursor.next_nodup(access),
+                }
+            },
+        };
+
+        let (key, first) = match try!(first.to_opt()) {
+            Some(kv) => kv,
+            None => {
+                self.pos = PagePos::Done;
+                return Ok(None);
+            },
+        };
+        self.pos = PagePos::InKey(key);
+        // GET_MULTIPLE produces nothing for a key with only one value, since
+        // it has no sub-database; the value is then a page by itself.
+        if try!(self.cursor.count()) > 1 {
+            Ok(Some((key, try!(self.cursor.get_multiple(access)))))
+        } else {
+            Ok(Some((key, first)))
+        }
+    }
+}
+
+/// An iterator over the duplicate values of a single key in a `DUPFIXED`
+/// database, a page at a time.
+///
+/// Each item is a slice of consecutive values of the key in sorted order,
+/// borrowed directly from the memory map, so no values are copied. Pages
+/// typically hold hundreds or thousands of values, depending on their size.
+///
+/// An error with code `INCOMPATIBLE` is produced if the database is not
+/// `DUPSORT | DUPFIXED`.
+///
+/// This is normally created with `ConstTransaction::iter_dup_pages_of`.
+#[derive(Debug)]
+pub struct DupPages<'access, 'txn: 'access, 'db: 'txn, V> {
+    inner: PageCursor<'access, 'txn, 'db>,
+    _v: PhantomData<fn () -> &'access V>,
+}
+
+impl<'access, 'txn: 'access, 'db: 'txn, V>
+DupPages<'access, 'txn, 'db, V> {
+    /// Creates an iterator over the value pages of `key` from the given
+    /// cursor and accessor.
+    ///
+    /// The cursor is positioned when the first item is requested; any prior
+    /// position is lost. `key` is copied into the iterator.
+    pub fn new<K : AsLmdbBytes + ?Sized>
+        (cursor: Cursor<'txn,'db>, access: &'access ConstAccessor<'txn>,
+         key: &K) -> Self
+    {
+        DupPages {
+            inner: PageCursor::new(
+                cursor, access, Some(key.as_lmdb_bytes().to_vec())),
+            _v: PhantomData,
+        }
+    }
+}
+
+impl<'access, 'txn: 'access, 'db: 'txn, V : LmdbRaw + 'access> Iterator
+for DupPages<'access, 'txn, 'db, V> {
+    type Item = Result<&'access [V]>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.inner.next_page() {
+            Ok(Some((_, page))) => Some(
+                <[V]>::from_lmdb_bytes(page).map_err(Error::ValRejected)),
+            Ok(None) => None,
+            Err(err) => Some(Err(err)),
+        }
+    }
+}
+
+/// An iterator over the duplicate values of every key in a `DUPFIXED`
+/// database, a page at a time.
+///
+/// This is like `DupPages`, but walks every key of the database in order,
+/// producing each page along with the key it belongs to.
+///
+/// This is normally created with `ConstTransaction::iter_dup_pages`.
+#[derive(Debug)]
+pub struct AllDupPages<'access, 'txn: 'access, 'db: 'txn, K : ?Sized, V> {
+    inner: PageCursor<'access, 'txn, 'db>,
+    _kv: PhantomData<fn () -> (&'access K, &'access V)>,
+}
+
+impl<'access, 'txn: 'access, 'db: 'txn, K : ?Sized, V>
+AllDupPages<'access, 'txn, 'db, K, V> {
+    /// Creates an iterator over all value pages of the database of the given
+    /// cursor.
+    ///
+    /// The cursor is positioned when the first item is requested; any prior
+    /// position is lost.
+    pub fn new(cursor: Cursor<'txn,'db>,
+               access: &'access ConstAccessor<'txn>) -> Self {
+        AllDupPages {
+            inner: PageCursor::new(cursor, access, None),
+            _kv: PhantomData,
+        }
+    }
+}
+
+impl<'access, 'txn: 'access, 'db: 'txn,
+     K : FromLmdbBytes + ?Sized + 'access, V : LmdbRaw + 'access> Iterator
+for AllDupPages<'access, 'txn, 'db, K, V> {
+    type Item = Result<(&'access K, &'access [V])>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.inner.next_page() {
+            Ok(Some((k, page))) => Some(Ok((
+                match K::from_lmdb_bytes(k) {
+                    Ok(k) => k,
+                    Err(err) => return Some(Err(Error::ValRejected(err))),
+                },
+                match <[V]>::from_lmdb_bytes(page) {
+                    Ok(page) => page,
+                    Err(err) => return Some(Err(Error::ValRejected(err))),
+                }))),
+            Ok(None) => None,
+            Err(err) => Some(Err(err)),
+        }
+    }
+}
@@ -128,6 +128,13 @@
 //! therefore includes a short discussion of how the lifetimes are intended to
 //! interact and how best to work with them.
 //!
+//! If the lifetimes get in the way, the `Environment` can instead be placed
+//! in an `Arc` and passed by value to `Database::open()` and the transaction
+//! constructors. The resulting `Database` and transaction handles own a
+//! reference to the environment and so have a `'static` lifetime; see
+//! `EnvRef` for details. A `Database<'static>` can in turn be placed in an
+//! `Arc` to be shared between components and threads.
+//!
 //! # Major Differences from the LMDB C API
 //!
 //! Databases cannot be created or destroyed within a transaction due to the
@@ -148,7 +155,7 @@
 //!
 //! # Non-Zero Cost
 //!
-//! There are three general areas where this wrapper adds non-zero-cost
+//! There are four general areas where this wrapper adds non-zero-cost
 //! abstractions:
 //!
 //! - Opening and closing databases adds locking overhead, since in LMDB it is
@@ -158,6 +165,10 @@
 //! - There is additional overhead in tracking what database handles are
 //!   currently open so that attempts to reopen one can be prevented.
 //!
+//! - Beginning and ending a transaction updates a count of active
+//!   transactions, so that `Environment::write()` can safely resize the
+//!   memory map.
+//!
 //! - Cursors and transactions track their owners separately. Additionally,
 //!   when two are used in conjunction, a runtime test is required to ensure
 //!   that they actually can be used together. This means that the handle
@@ -221,14 +232,19 @@
 //! - `mdb_set_relfunc`, `mdb_set_relctx`: Currently a noop in LMDB. Even if it
 //! weren't, it is unlikely that there is any remotely safe or convenient way
 //! to provide an interface to it.
-//!
-//! - `mdb_reader_list`: Doesn't seem useful enough to expose.
 
 #![deny(missing_docs)]
 
 extern crate liblmdb_sys as ffi;
 extern crate libc;
 #[macro_use] extern crate bitflags;
+#[cfg(feature = "serde")] extern crate serde;
+#[cfg(feature = "serde")] extern crate bincode;
+#[cfg(feature = "serde")] extern crate serde_json;
+#[cfg(feature = "compression")] extern crate lz4_flex;
+#[cfg(feature = "checksum")] extern crate crc32c;
+#[cfg(feature = "checksum")] extern crate xxhash_rust;
+#[cfg(feature = "derive")] extern crate lmdb_zero_derive;
 
 use std::ffi::CStr;
 
@@ -239,7 +255,14 @@ macro_rules! lmdb_call {
     ($x:expr) => { {
         let code = $x;
         if 0 != code {
-            return Err($crate::Error::Code(code));
+            return Err($crate::Error::from_code(code));
+        }
+    } };
+    // Passes any error through `$context` to attach an `ErrorContext`
+    ($x:expr, $context:expr) => { {
+        let code = $x;
+        if 0 != code {
+            return Err($context($crate::Error::from_code(code)));
         }
     } }
 }
@@ -276,10 +299,14 @@ mod mdb_vals;
 mod ffi2;
 
 pub mod error;
-pub use error::{Error, Result};
+pub use error::{Error, ErrorContext, Result};
 
 mod env;
-pub use env::{open, copy, EnvBuilder, Environment, Stat, EnvInfo};
+pub use env::{open, copy, EnvBuilder, Environment, EnvRef, Stat, EnvInfo};
+pub use env::{DatabaseInfo, ReaderInfo};
+pub use env::RetryPolicy;
+mod readers;
+pub use readers::TrackedReader;
 
 mod dbi;
 pub use dbi::{db, Database, DatabaseOptions};
@@ -287,15 +314,43 @@ pub use dbi::{db, Database, DatabaseOptions};
 pub mod traits;
 mod unaligned;
 pub use unaligned::{Unaligned, unaligned};
+mod bigendian;
+pub use bigendian::{BigEndian, BigEndianInt};
+mod tuplekey;
+pub use tuplekey::{KeyPart, FromKeyPart, KeyTuple, FromKeyTuple};
+pub use tuplekey::{encode_key, decode_key};
 
 mod tx;
 pub use tx::{ConstTransaction, ReadTransaction, WriteTransaction};
 pub use tx::ResetTransaction;
 pub use tx::{ConstAccessor, WriteAccessor};
 pub use tx::{put, del};
+mod pool;
+pub use pool::{ReadTransactionPool, PooledReadTransaction};
 
 mod cursor;
 pub use cursor::{StaleCursor, Cursor};
 
+mod typed;
+pub use typed::{TypedDatabase, TypedDatabaseOptions, TypedCursor};
+
 mod iter;
-pub use iter::{CursorIter, MaybeOwned};
+pub use iter::{CursorIter, MaybeOwned, RangeIter, PrefixIter, PrefixKeys};
+pub use iter::{DupIter, DupGroups, DupPages, AllDupPages};
+
+#[cfg(feature = "serde")] mod codec;
+#[cfg(feature = "serde")]
+pub use codec::{Serde, SerdeBuf, Deserialized, Codec, Bincode, Json};
+
+#[cfg(feature = "compression")] mod compress;
+#[cfg(feature = "compression")]
+pub use compress::{Compressed, CompressedBuf, Decompressed};
+
+#[cfg(feature = "checksum")] mod checksum;
+#[cfg(feature = "checksum")]
+pub use checksum::{Checksummed, ChecksummedBuf, Checksum, Crc32c, XxHash64};
+#[cfg(feature = "checksum")]
+pub use checksum::scan_checksums;
+
+#[cfg(feature = "derive")]
+pub use lmdb_zero_derive::{LmdbRaw, LmdbOrdKey};
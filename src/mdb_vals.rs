@@ -46,6 +46,13 @@ pub fn from_val<'a, O, V : FromLmdbBytes + ?Sized>(
     V::from_lmdb_bytes(bytes).map_err(|s| Error::ValRejected(s))
 }
 
+pub fn from_val_owned<'a, O, V : FromLmdbBytesOwned<'a>>(
+    _owner: &'a O, val: &ffi::MDB_val) -> Result<V>
+{
+    let bytes = mdb_val_as_bytes(_owner, val);
+    V::from_lmdb_bytes_owned(bytes).map_err(Error::ValRejected)
+}
+
 pub unsafe fn from_reserved<'a, O, V : FromReservedLmdbBytes + ?Sized>(
     _owner: &'a O, val: &ffi::MDB_val) -> &'a mut V
 {
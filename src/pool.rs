@@ -0,0 +1,196 @@
+// Copyright 2016 FullContact, Inc
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use std::fmt;
+use std::ops::Deref;
+use std::sync::Mutex;
+
+use env::{self, EnvRef};
+use error::{Error, Result};
+use tx::{ReadTransaction, ResetTransaction};
+
+// A reset transaction waiting in the pool.
+//
+// Transactions are not normally `Send`, since LMDB ties read transactions to
+// the thread which created them. With `NOTLS`, which `ReadTransactionPool`
+// requires, read transactions may be used by any thread, one at a time.
+struct IdleTransaction<'env>(ResetTransaction<'env>);
+unsafe impl<'env> Send for IdleTransaction<'env> { }
+
+/// A thread-safe pool of read transactions.
+///
+/// Creating a `ReadTransaction` allocates, and must acquire a slot in the
+/// reader lock table. Renewing a reset transaction is much cheaper. The pool
+/// keeps the transactions handed out by `get()` once they are dropped, in
+/// reset state, and renews them for later calls to `get()`.
+///
+/// Since the transactions move between threads, the environment must be
+/// opened with `open::NOTLS`. Note that each pooled transaction keeps its
+/// slot in the reader lock table, so the pool can hold as many slots as the
+/// number of transactions that were in use at once.
+///
+/// ## Example
+///
+/// ```
+/// extern crate lmdb_zero as lmdb;
+/// extern crate tempdir;
+///
+/// # fn main() {
+/// use std::sync::Arc;
+/// use std::thread;
+///
+/// # let tmp = tempdir::TempDir::new_in(".", "lmdbzero").unwrap();
+/// # let path = tmp.path().to_str().unwrap();
+/// let env = Arc::new(unsafe {
+///   lmdb::EnvBuilder::new().unwrap()
+///     .open(path, lmdb::open::NOTLS, 0o600).unwrap()
+/// });
+/// let db = Arc::new(lmdb::Database::open(
+///   env.clone(), None, &lmdb::DatabaseOptions::defaults()).unwrap());
+/// {
+///   let txn = lmdb::WriteTransaction::new(&*env).unwrap();
+///   txn.access().put(&db, "city", "Tallinn", lmdb::put::Flags::empty())
+///     .unwrap();
+///   txn.commit().unwrap();
+/// }
+///
+/// let pool = Arc::new(lmdb::ReadTransactionPool::new(env.clone()).unwrap());
+/// let threads: Vec<_> = (0..4).map(|_| {
+///   let (pool, db) = (pool.clone(), db.clone());
+///   thread::spawn(move || {
+///     for _ in 0..10 {
+///       let txn = pool.get().unwrap();
+///       let access = txn.access();
+///       assert_eq!("Tallinn", access.get::<str,str>(&db, "city").unwrap());
+///     }
+///   })
+/// }).collect();
+/// for thread in threads {
+///   thread.join().unwrap();
+/// }
+/// assert!(pool.idle() >= 1 && pool.idle() <= 4);
+/// # }
+/// ```
+pub struct ReadTransactionPool<'env> {
+    env: EnvRef<'env>,
+    idle: Mutex<Vec<IdleTransaction<'env>>>,
+}
+
+impl<'env> fmt::Debug for ReadTransactionPool<'env> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ReadTransactionPool")
+            .field("env", &self.env)
+            .field("idle", &self.idle())
+            .finish()
+    }
+}
+
+impl<'env> ReadTransactionPool<'env> {
+    /// Creates an empty pool of read transactions on `env`.
+    ///
+    /// `env` may be either an `&Environment` or an `Arc<Environment>`, as
+    /// with `ReadTransaction::new()`.
+    ///
+    /// ## Errors
+    ///
+    /// Returns `Error::NoTls` if `env` was not opened with `open::NOTLS`.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// # include!("src/example_helpers.rs");
+    /// # fn main() {
+    /// # let env = create_env();
+    /// // `create_env()` does not use `NOTLS`
+    /// assert_eq!(lmdb::Error::NoTls,
+    ///            lmdb::ReadTransactionPool::new(&env).unwrap_err());
+    /// # }
+    /// ```
+    pub fn new<E : Into<EnvRef<'env>>>(env: E) -> Result<Self> {
+        let env = env.into();
+        if !try!(env.flags()).contains(env::open::NOTLS) {
+            return Err(Error::NoTls);
+        }
+
+        Ok(ReadTransactionPool {
+            env: env,
+            idle: Mutex::new(Vec::new()),
+        })
+    }
+
+    /// Returns a read transaction on the latest snapshot of the environment.
+    ///
+    /// A transaction from the pool is renewed if one is available; otherwise
+    /// a new one is created. The transaction returns to the pool when the
+    /// guard is dropped.
+    pub fn get(&self) -> Result<PooledReadTransaction<'_, 'env>> {
+        let idle = self.idle.lock().expect("idle lock poisoned").pop();
+        let txn = match idle {
+            Some(IdleTransaction(reset)) => try!(reset.renew()),
+            None => try!(ReadTransaction::new(self.env.clone())),
+        };
+
+        Ok(PooledReadTransaction {
+            pool: self,
+            txn: Some(txn),
+        })
+    }
+
+    /// Returns the number of reset transactions waiting in the pool.
+    pub fn idle(&self) -> usize {
+        self.idle.lock().expect("idle lock poisoned").len()
+    }
+
+    /// Drops all transactions waiting in the pool, releasing their slots in
+    /// the reader lock table.
+    ///
+    /// Transactions currently in use still return to the pool when dropped.
+    pub fn clear(&self) {
+        // Drop the transactions outside the lock
+        let idle = {
+            let mut locked = self.idle.lock().expect("idle lock poisoned");
+            locked.drain(..).collect::<Vec<_>>()
+        };
+        drop(idle);
+    }
+}
+
+/// A read transaction handed out by `ReadTransactionPool::get()`.
+///
+/// This dereferences to `ReadTransaction`. When dropped, the transaction is
+/// reset and returned to the pool.
+pub struct PooledReadTransaction<'pool, 'env : 'pool> {
+    pool: &'pool ReadTransactionPool<'env>,
+    // Always `Some` until dropped.
+    txn: Option<ReadTransaction<'env>>,
+}
+
+impl<'pool, 'env> fmt::Debug for PooledReadTransaction<'pool, 'env> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("PooledReadTransaction")
+            .field("txn", &self.txn)
+            .finish()
+    }
+}
+
+impl<'pool, 'env> Deref for PooledReadTransaction<'pool, 'env> {
+    type Target = ReadTransaction<'env>;
+
+    fn deref(&self) -> &ReadTransaction<'env> {
+        self.txn.as_ref().expect("pooled transaction already returned")
+    }
+}
+
+impl<'pool, 'env> Drop for PooledReadTransaction<'pool, 'env> {
+    fn drop(&mut self) {
+        if let Some(txn) = self.txn.take() {
+            let reset = IdleTransaction(txn.reset());
+            self.pool.idle.lock().expect("idle lock poisoned").push(reset);
+        }
+    }
+}
@@ -0,0 +1,388 @@
+// Copyright 2016 FullContact, Inc
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use std::backtrace::Backtrace;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::thread;
+use std::time::{Duration, Instant};
+use libc::c_int;
+
+use ffi;
+use error::{Error, Result};
+
+/// A read transaction which is currently open, as recorded when tracking is
+/// enabled with `EnvBuilder::set_reader_tracking()`.
+///
+/// Only top-level read transactions are tracked. A transaction stops being
+/// tracked when it is reset or dropped, and is tracked anew (with a new
+/// `opened` time and no label) when renewed.
+#[derive(Debug,Clone)]
+pub struct TrackedReader {
+    /// ID of the snapshot held by the transaction (see
+    /// `ConstTransaction::id()`)
+    pub id: usize,
+    /// When the transaction was opened or renewed
+    pub opened: Instant,
+    /// Label set with `ReadTransaction::set_label()`, if any
+    pub label: Option<String>,
+    /// Where the transaction was opened, if backtraces are being captured
+    pub backtrace: Option<Arc<Backtrace>>,
+}
+
+impl TrackedReader {
+    /// Returns how long the transaction has been open.
+    pub fn age(&self) -> Duration {
+        self.opened.elapsed()
+    }
+}
+
+// Callback invoked for read transactions open for too long.
+type OldReaderCallback = Box<dyn Fn (&TrackedReader) + Send + Sync>;
+
+// Reader tracking configuration accumulated by `EnvBuilder`.
+pub struct TrackingConfig {
+    capture_backtraces: bool,
+    old_reader: Option<(Duration, OldReaderCallback)>,
+}
+
+impl fmt::Debug for TrackingConfig {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("TrackingConfig")
+            .field("capture_backtraces", &self.capture_backtraces)
+            .field("old_reader_threshold",
+                   &self.old_reader.as_ref().map(|o| o.0))
+            .finish()
+    }
+}
+
+impl TrackingConfig {
+    pub fn new() -> Self {
+        TrackingConfig {
+            capture_backtraces: false,
+            old_reader: None,
+        }
+    }
+
+    pub fn set_capture_backtraces(&mut self, capture: bool) {
+        self.capture_backtraces = capture;
+    }
+
+    pub fn set_old_reader_callback(&mut self, threshold: Duration,
+                                   callback: OldReaderCallback) {
+        self.old_reader = Some((threshold, callback));
+    }
+}
+
+struct TrackerState {
+    next_key: usize,
+    // Each reader, along with whether it has been passed to the old reader
+    // callback yet.
+    readers: HashMap<usize, (TrackedReader, bool)>,
+    shutdown: bool,
+}
+
+struct TrackerShared {
+    capture_backtraces: bool,
+    old_reader: Option<(Duration, OldReaderCallback)>,
+    state: Mutex<TrackerState>,
+    // Signalled when a reader is added, or on shutdown, to wake the watchdog.
+    cond: Condvar,
+}
+
+impl TrackerShared {
+    fn lock(&self) -> MutexGuard<'_, TrackerState> {
+        self.state.lock().expect("reader tracker lock poisoned")
+    }
+}
+
+/// Records the read transactions open on an environment.
+pub struct ReaderTracker {
+    shared: Arc<TrackerShared>,
+    watchdog: Option<thread::JoinHandle<()>>,
+}
+
+impl fmt::Debug for ReaderTracker {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ReaderTracker")
+            .field("readers", &self.shared.lock().readers.len())
+            .finish()
+    }
+}
+
+impl ReaderTracker {
+    // Starts tracking, including the watchdog thread if there is an old
+    // reader callback.
+    pub fn new(config: TrackingConfig) -> Self {
+        let has_callback = config.old_reader.is_some();
+        let shared = Arc::new(TrackerShared {
+            capture_backtraces: config.capture_backtraces,
+            old_reader: config.old_reader,
+            state: Mutex::new(TrackerState {
+                next_key: 0,
+                readers: HashMap::new(),
+                shutdown: false,
+            }),
+            cond: Condvar::new(),
+        });
+
+        let watchdog = if has_callback {
+            let shared = shared.clone();
+            Some(thread::Builder::new()
+                 .name("lmdb-reader-watchdog".to_owned())
+                 .spawn(move || watchdog(&shared))
+                 .expect("failed to spawn reader watchdog thread"))
+        } else {
+            None
+        };
+
+        ReaderTracker {
+            shared: shared,
+            watchdog: watchdog,
+        }
+    }
+
+    // Starts tracking a read transaction holding snapshot `id`, returning
+    // the key to pass to `untrack()` and `set_label()`.
+    pub fn track(&self, id: usize) -> usize {
+        let backtrace = if self.shared.capture_backtraces {
+            Some(Arc::new(Backtrace::force_capture()))
+        } else {
+            None
+        };
+
+        let mut state = self.shared.lock();
+        let key = state.next_key;
+        state.next_key = state.next_key.wrapping_add(1);
+        state.readers.insert(key, (TrackedReader {
+            id: id,
+            opened: Instant::now(),
+            label: None,
+            backtrace: backtrace,
+        }, false));
+        self.shared.cond.notify_all();
+        key
+    }
+
+    pub fn untrack(&self, key: usize) {
+        self.shared.lock().readers.remove(&key);
+    }
+
+    pub fn set_label(&self, key: usize, label: &str) {
+        if let Some(entry) = self.shared.lock().readers.get_mut(&key) {
+            entry.0.label = Some(label.to_owned());
+        }
+    }
+
+    // Returns the tracked readers open for at least `threshold`, oldest
+    // first.
+    pub fn readers(&self, threshold: Duration) -> Vec<TrackedReader> {
+        let now = Instant::now();
+        let mut ret: Vec<TrackedReader> = self.shared.lock().readers.values()
+            .map(|entry| &entry.0)
+            .filter(|reader| now.duration_since(reader.opened) >= threshold)
+            .cloned().collect();
+        ret.sort_by_key(|reader| reader.opened);
+        ret
+    }
+}
+
+impl Drop for ReaderTracker {
+    fn drop(&mut self) {
+        self.shared.lock().shutdown = true;
+        self.shared.cond.notify_all();
+        if let Some(watchdog) = self.watchdog.take() {
+            // A panic in the callback has already been reported by the
+            // thread itself.
+            let _ = watchdog.join();
+        }
+    }
+}
+
+// Body of the watchdog thread, which passes each reader to the old reader
+// callback once it has been open for longer than the threshold.
+fn watchdog(shared: &TrackerShared) {
+    let &(threshold, ref callback) = shared.old_reader.as_ref()
+        .expect("watchdog started without callback");
+
+    let mut state = shared.lock();
+    while !state.shutdown {
+        let now = Instant::now();
+        let mut due = Vec::new();
+        let mut next_deadline = None;
+        for entry in state.readers.values_mut() {
+            if entry.1 {
+                continue;
+            }
+
+            // A threshold too large to represent is never reached.
+            let deadline = match entry.0.opened.checked_add(threshold) {
+                Some(deadline) => deadline,
+                None => continue,
+            };
+            if deadline <= now {
+                entry.1 = true;
+                due.push(entry.0.clone());
+            } else {
+                next_deadline = Some(next_deadline.map_or(
+                    deadline, |next: Instant| next.min(deadline)));
+            }
+        }
+
+        if !due.is_empty() {
+            // Don't hold the lock while running the callback, so that it can
+            // itself use the environment.
+            drop(state);
+            due.sort_by_key(|reader| reader.opened);
+            for reader in &due {
+                callback(reader);
+            }
+            state = shared.lock();
+            continue;
+        }
+
+        state = match next_deadline {
+            Some(deadline) => shared.cond.wait_timeout(
+                state, deadline.saturating_duration_since(now))
+                .expect("reader tracker lock poisoned").0,
+            None => shared.cond.wait(state)
+                .expect("reader tracker lock poisoned"),
+        };
+    }
+}
+
+// Callback invoked with the result of each reader check which cleared slots
+// or failed.
+type ReaperCallback = Box<dyn Fn (Result<i32>) + Send + Sync>;
+
+// Stale reader reaper configuration accumulated by `EnvBuilder`.
+pub struct ReaperConfig {
+    interval: Duration,
+    callback: ReaperCallback,
+}
+
+impl fmt::Debug for ReaperConfig {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ReaperConfig")
+            .field("interval", &self.interval)
+            .finish()
+    }
+}
+
+impl ReaperConfig {
+    pub fn new(interval: Duration, callback: ReaperCallback) -> Self {
+        ReaperConfig {
+            interval: interval,
+            callback: callback,
+        }
+    }
+}
+
+// The environment pointer passed to the reaper thread.
+//
+// `mdb_reader_check()` may be called from any thread, and `ReaderReaper`
+// ensures the thread is stopped before the environment is closed.
+struct EnvPtr(*mut ffi::MDB_env);
+unsafe impl Send for EnvPtr { }
+
+/// Background thread periodically clearing stale entries from the reader
+/// lock table of an environment.
+pub struct ReaderReaper {
+    // Set to true, and the condvar signalled, to stop the thread.
+    shutdown: Arc<(Mutex<bool>, Condvar)>,
+    thread: Option<thread::JoinHandle<()>>,
+}
+
+impl fmt::Debug for ReaderReaper {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ReaderReaper").finish()
+    }
+}
+
+impl ReaderReaper {
+    // Starts the reaper thread on `env`.
+    //
+    // Unsafe because the caller must drop the result before closing `env`.
+    pub unsafe fn new(env: *mut ffi::MDB_env, config: ReaperConfig)
+                      -> Self {
+        let shutdown = Arc::new((Mutex::new(false), Condvar::new()));
+        let thread = {
+            let shutdown = shutdown.clone();
+            let env = EnvPtr(env);
+            thread::Builder::new()
+                .name("lmdb-reader-reaper".to_owned())
+                .spawn(move || reaper(&env, &config, &shutdown))
+                .expect("failed to spawn reader reaper thread")
+        };
+
+        ReaderReaper {
+            shutdown: shutdown,
+            thread: Some(thread),
+        }
+    }
+}
+
+impl Drop for ReaderReaper {
+    fn drop(&mut self) {
+        *self.shutdown.0.lock().expect("reaper lock poisoned") = true;
+        self.shutdown.1.notify_all();
+        if let Some(thread) = self.thread.take() {
+            // A panic in the callback has already been reported by the
+            // thread itself.
+            let _ = thread.join();
+        }
+    }
+}
+
+// Body of the reaper thread.
+fn reaper(env: &EnvPtr, config: &ReaperConfig,
+          shutdown: &(Mutex<bool>, Condvar)) {
+    let mut stop = shutdown.0.lock().expect("reaper lock poisoned");
+    loop {
+        // An interval too large to represent is never reached, so just wait
+        // to be stopped.
+        let deadline = match Instant::now().checked_add(config.interval) {
+            Some(deadline) => deadline,
+            None => {
+                while !*stop {
+                    stop = shutdown.1.wait(stop)
+                        .expect("reaper lock poisoned");
+                }
+                return;
+            },
+        };
+        while !*stop {
+            let now = Instant::now();
+            if now >= deadline {
+                break;
+            }
+            stop = shutdown.1.wait_timeout(stop, deadline - now)
+                .expect("reaper lock poisoned").0;
+        }
+        if *stop {
+            return;
+        }
+
+        let mut dead: c_int = 0;
+        let result = match unsafe {
+            ffi::mdb_reader_check(env.0, &mut dead)
+        } {
+            0 => Ok(dead as i32),
+            code => Err(Error::from_code(code)),
+        };
+
+        if Ok(0) != result {
+            // Release the lock while running the callback so that dropping
+            // the environment does not have to wait for it to start.
+            drop(stop);
+            (config.callback)(result);
+            stop = shutdown.0.lock().expect("reaper lock poisoned");
+        }
+    }
+}
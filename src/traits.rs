@@ -11,10 +11,14 @@
 //! This exists as a separate module solely so that it can be wildcard imported
 //! where necessary.
 
+use std::borrow::Cow;
 use std::char;
 use std::cmp::Ord;
 use std::ffi::CStr;
 use std::mem;
+use std::ptr;
+use std::num::{NonZeroI8, NonZeroI16, NonZeroI32, NonZeroI64};
+use std::num::{NonZeroU8, NonZeroU16, NonZeroU32, NonZeroU64};
 use std::num::Wrapping;
 use std::slice;
 use std::str;
@@ -65,6 +69,30 @@ pub trait FromLmdbBytes {
     fn from_lmdb_bytes(&[u8]) -> Result<&Self, String>;
 }
 
+/// Like `FromLmdbBytes`, but produces a value which does not (necessarily)
+/// borrow from the database, and so can outlive the accessor it was read
+/// through.
+///
+/// The lifetime `'a` is that of the bytes in the database, which allows
+/// `Cow` to borrow where possible. Implementations are provided for:
+///
+/// - `String` and `Box<str>`, which fail if the value is not valid UTF-8.
+///
+/// - `Vec<V>` and `Box<[V]>` for any `LmdbRaw` or `LmdbRawIfUnaligned` `V`,
+///   which fail if the size of the value is not a multiple of the size of
+///   `V`. Unlike `[V]`, these do not care about the alignment of the value.
+///
+/// - `Cow<str>`, which always borrows, and `Cow<[V]>` for any `LmdbRaw` `V`,
+///   which borrows if the value is suitably aligned and copies otherwise.
+///
+/// This is used by `ConstAccessor::get_owned()` and the `_owned` variants of
+/// the `Cursor` functions, such as `Cursor::next_owned()`.
+pub trait FromLmdbBytesOwned<'a> : Sized {
+    /// Given a byte slice, return the `Self` it describes, or `Err` with an
+    /// error message if the given byte slice is not an appropriate value.
+    fn from_lmdb_bytes_owned(bytes: &'a [u8]) -> Result<Self, String>;
+}
+
 /// Like `FromLmdbBytes`, but can be used with `put_reserve()` calls.
 ///
 /// A blanket implementation is provided for anything which is `LmdbRaw`.
@@ -184,6 +212,13 @@ pub trait FromReservedLmdbBytes {
 /// be written into the database and thus remain visible to puns and could leak
 /// information.
 ///
+/// ## Deriving
+///
+/// The `lmdb-zero-derive` crate (re-exported with the `derive` feature)
+/// provides `#[derive(LmdbRaw)]`, which rejects structs with padding, without
+/// a defined layout, or with fields that are not themselves `LmdbRaw`, and
+/// `#[derive(LmdbOrdKey)]`, which compares structs field by field.
+///
 /// ## Example
 ///
 /// ```
@@ -237,6 +272,78 @@ unsafe impl<T : LmdbRaw> LmdbRawIfUnaligned for T {
     }
 }
 
+/// Like `LmdbRaw`, but for types where not every bit pattern is a valid
+/// value, such as `bool`, `char`, the `NonZero*` integers and fieldless
+/// enums.
+///
+/// Values are still stored by copying them in, but `FromLmdbBytes` checks
+/// the bytes with `check_lmdb_bytes()` before reinterpreting them, so reading
+/// bad data fails with `Error::ValRejected` instead of producing an invalid
+/// value.
+///
+/// Since there cannot be a blanket implementation alongside the one for
+/// `LmdbRaw`, implementing this trait does not by itself provide
+/// `AsLmdbBytes` and `FromLmdbBytes`; the `lmdb_raw_checked!` macro does
+/// that, and `lmdb_enum!` implements everything for a fieldless enum.
+///
+/// The same caveats with respect to alignment and padding as for `LmdbRaw`
+/// apply.
+///
+/// ## Unsafety
+///
+/// Behaviour is undefined if `check_lmdb_bytes()` accepts a bit pattern which
+/// is not a valid value of the type.
+///
+/// ## Example
+///
+/// ```
+/// #[macro_use] extern crate lmdb_zero;
+/// # include!("src/example_helpers.rs");
+///
+/// #[repr(u8)]
+/// #[derive(Clone, Copy, Debug, PartialEq)]
+/// enum Colour { Red = 1, Green = 2, Blue = 4 }
+/// lmdb_enum!(Colour : u8 { Red, Green, Blue });
+///
+/// # fn main() {
+/// # let env = create_env();
+/// # let db = defdb(&env);
+/// let txn = lmdb::WriteTransaction::new(&env).unwrap();
+/// {
+///   let mut access = txn.access();
+///   let f = lmdb::put::Flags::empty();
+///   access.put(&db, "colour", &Colour::Blue, f).unwrap();
+///   access.put(&db, "flag", &true, f).unwrap();
+///   access.put(&db, "bad", &3u8, f).unwrap();
+///
+///   assert_eq!(Colour::Blue,
+///              *access.get::<str,Colour>(&db, "colour").unwrap());
+///   assert!(*access.get::<str,bool>(&db, "flag").unwrap());
+///   // 3 is neither a valid `Colour` nor a valid `bool`
+///   match access.get::<str,Colour>(&db, "bad") {
+///     Err(lmdb::Error::ValRejected(_)) => (),
+///     r => panic!("Unexpected result: {:?}", r),
+///   }
+///   assert!(access.get::<str,bool>(&db, "bad").is_err());
+/// }
+/// txn.commit().unwrap();
+/// # }
+/// ```
+pub unsafe trait LmdbRawChecked : Copy + Sized {
+    /// Returns the name of this type to report in error messages.
+    ///
+    /// If not implemented, defaults to `"?"`.
+    fn reported_type() -> String {
+        "?".to_owned()
+    }
+
+    /// Checks whether `bytes` hold a valid value of this type, returning
+    /// `Err` with an error message if not.
+    ///
+    /// `bytes` is guaranteed to have the size and alignment of `Self`.
+    fn check_lmdb_bytes(bytes: &[u8]) -> Result<(), String>;
+}
+
 /// Trait describing a value which can be used as an LMDB key by having LMDB
 /// call into the value's `Ord` implementation.
 ///
@@ -437,27 +544,67 @@ impl<V : LmdbRaw> AsLmdbBytes for V {
     }
 }
 
-impl<V: LmdbRaw> FromLmdbBytes for V {
-    fn from_lmdb_bytes(bytes: &[u8]) -> Result<&Self, String> {
-        let size = mem::size_of::<V>();
-        let align = mem::align_of::<V>();
+// Checks that `bytes` has the size and alignment of a single `V`.
+//
+// `reported_type` is only called to format an error.
+fn check_raw_value<V>(bytes: &[u8], reported_type: fn () -> String)
+                      -> Result<(), String> {
+    let size = mem::size_of::<V>();
+    let align = mem::align_of::<V>();
+
+    if bytes.len() != size {
+        return Err(
+            format!("Type {} is size {}, but byte array has size {}",
+                    reported_type(), size, bytes.len()));
+    }
 
-        if bytes.len() != size {
-            return Err(
-                format!("Type {} is size {}, but byte array has size {}",
-                        V::reported_type(), size, bytes.len()));
-        }
+    let misalign = (bytes.as_ptr() as usize) % align;
+    if 0 != misalign {
+        return Err(
+            format!("Type {} requires alignment {}, but byte array \
+                     at {:08x} is misaligned by {} bytes \
+                     (see https://api.fullcontact.com/v3/docs/rustdoc/\
+                     lmdb_zero/traits/trait.LmdbRaw.html#alignment)",
+                    reported_type(), align,
+                    (bytes.as_ptr() as usize), misalign));
+    }
 
-        let misalign = (bytes.as_ptr() as usize) % align;
-        if 0 != misalign {
-            return Err(
-                format!("Type {} requires alignment {}, but byte array \
-                         at {:08x} is misaligned by {} bytes \
-                         (see https://api.fullcontact.com/v3/docs/rustdoc/\
-                         lmdb_zero/traits/trait.LmdbRaw.html#alignment)",
-                        V::reported_type(), align,
-                        (bytes.as_ptr() as usize), misalign));
-        }
+    Ok(())
+}
+
+// Checks that `bytes` has the size and alignment of a slice of `V`.
+//
+// `reported_type` is only called to format an error.
+fn check_raw_slice<V>(bytes: &[u8], reported_type: fn () -> String)
+                      -> Result<(), String> {
+    let size = mem::size_of::<V>();
+    let align = mem::align_of::<V>();
+
+    let size_mod = bytes.len() % size;
+    if 0 != size_mod {
+        return Err(
+            format!("Type [{}] must have a size which is a multiple \
+                     of {}, but byte array has size {} ({} trailing bytes)",
+                    reported_type(), size, bytes.len(), size_mod));
+    }
+
+    let misalign = (bytes.as_ptr() as usize) % align;
+    if 0 != misalign {
+        return Err(
+            format!("Type [{}] requires alignment {}, but byte array \
+                     at {:08x} is misaligned by {} bytes \
+                     (see https://api.fullcontact.com/v3/docs/rustdoc/\
+                     lmdb_zero/traits/trait.LmdbRaw.html#alignment)",
+                    reported_type(), align,
+                    (bytes.as_ptr() as usize), misalign));
+    }
+
+    Ok(())
+}
+
+impl<V: LmdbRaw> FromLmdbBytes for V {
+    fn from_lmdb_bytes(bytes: &[u8]) -> Result<&Self, String> {
+        try!(check_raw_value::<V>(bytes, V::reported_type));
 
         Ok(unsafe {
             mem::transmute(bytes.as_ptr())
@@ -483,32 +630,12 @@ impl<V : LmdbRaw> AsLmdbBytes for [V] {
 
 impl<V : LmdbRaw> FromLmdbBytes for [V] {
     fn from_lmdb_bytes(bytes: &[u8]) -> Result<&Self, String> {
-        let size = mem::size_of::<V>();
-        let align = mem::align_of::<V>();
-
-        let size_mod = bytes.len() % size;
-        if 0 != size_mod {
-            return Err(
-                format!("Type [{}] must have a size which is a multiple \
-                         of {}, but byte array has size {} ({} trailing bytes)",
-                        V::reported_type(), size, bytes.len(), size_mod));
-        }
-
-        let misalign = (bytes.as_ptr() as usize) % align;
-        if 0 != misalign {
-            return Err(
-                format!("Type [{}] requires alignment {}, but byte array \
-                         at {:08x} is misaligned by {} bytes \
-                         (see https://api.fullcontact.com/v3/docs/rustdoc/\
-                         lmdb_zero/traits/trait.LmdbRaw.html#alignment)",
-                        V::reported_type(), align,
-                        (bytes.as_ptr() as usize), misalign));
-        }
+        try!(check_raw_slice::<V>(bytes, V::reported_type));
 
         unsafe {
             Ok(slice::from_raw_parts(
                 bytes.as_ptr() as *const V,
-                bytes.len() / size))
+                bytes.len() / mem::size_of::<V>()))
         }
     }
 }
@@ -568,6 +695,64 @@ impl<V : LmdbRaw> AsLmdbBytes for Vec<V> {
     }
 }
 
+impl<'a> FromLmdbBytesOwned<'a> for String {
+    fn from_lmdb_bytes_owned(bytes: &'a [u8]) -> Result<Self, String> {
+        str::from_lmdb_bytes(bytes).map(str::to_owned)
+    }
+}
+
+impl<'a> FromLmdbBytesOwned<'a> for Box<str> {
+    fn from_lmdb_bytes_owned(bytes: &'a [u8]) -> Result<Self, String> {
+        str::from_lmdb_bytes(bytes).map(Box::from)
+    }
+}
+
+impl<'a> FromLmdbBytesOwned<'a> for Cow<'a, str> {
+    fn from_lmdb_bytes_owned(bytes: &'a [u8]) -> Result<Self, String> {
+        str::from_lmdb_bytes(bytes).map(Cow::Borrowed)
+    }
+}
+
+impl<'a, V : LmdbRawIfUnaligned> FromLmdbBytesOwned<'a> for Vec<V> {
+    fn from_lmdb_bytes_owned(bytes: &'a [u8]) -> Result<Self, String> {
+        let size = mem::size_of::<V>();
+        let size_mod = bytes.len() % size;
+        if 0 != size_mod {
+            return Err(
+                format!("Type [{}] must have a size which is a multiple \
+                         of {}, but byte array has size {} ({} trailing bytes)",
+                        V::reported_type(), size, bytes.len(), size_mod));
+        }
+
+        // Copy byte-wise, since `bytes` need not be aligned for `V`.
+        let len = bytes.len() / size;
+        let mut ret = Vec::<V>::with_capacity(len);
+        unsafe {
+            ptr::copy_nonoverlapping(
+                bytes.as_ptr(), ret.as_mut_ptr() as *mut u8, bytes.len());
+            ret.set_len(len);
+        }
+        Ok(ret)
+    }
+}
+
+impl<'a, V : LmdbRawIfUnaligned> FromLmdbBytesOwned<'a> for Box<[V]> {
+    fn from_lmdb_bytes_owned(bytes: &'a [u8]) -> Result<Self, String> {
+        Vec::<V>::from_lmdb_bytes_owned(bytes).map(Vec::into_boxed_slice)
+    }
+}
+
+impl<'a, V : LmdbRaw> FromLmdbBytesOwned<'a> for Cow<'a, [V]> {
+    fn from_lmdb_bytes_owned(bytes: &'a [u8]) -> Result<Self, String> {
+        // If the bytes cannot be borrowed, fall back to copying, which fails
+        // only if the size is wrong.
+        match <[V]>::from_lmdb_bytes(bytes) {
+            Ok(borrowed) => Ok(Cow::Borrowed(borrowed)),
+            Err(_) => Vec::<V>::from_lmdb_bytes_owned(bytes).map(Cow::Owned),
+        }
+    }
+}
+
 static IGNORE: Ignore = Ignore;
 
 impl FromLmdbBytes for Ignore {
@@ -576,22 +761,181 @@ impl FromLmdbBytes for Ignore {
     }
 }
 
-impl AsLmdbBytes for char {
-    fn as_lmdb_bytes(&self) -> &[u8] {
-        unsafe {
-            slice::from_raw_parts(
-                self as *const char as *const u8,
-                mem::size_of::<char>())
+#[doc(hidden)]
+pub fn checked_as_lmdb_bytes<V : LmdbRawChecked>(v: &V) -> &[u8] {
+    unsafe {
+        slice::from_raw_parts(v as *const V as *const u8, mem::size_of::<V>())
+    }
+}
+
+#[doc(hidden)]
+pub fn checked_from_lmdb_bytes<V : LmdbRawChecked>(bytes: &[u8])
+                                                   -> Result<&V, String> {
+    try!(check_raw_value::<V>(bytes, V::reported_type));
+    try!(V::check_lmdb_bytes(bytes));
+
+    Ok(unsafe { &*(bytes.as_ptr() as *const V) })
+}
+
+/// Implements `AsLmdbBytes` and `FromLmdbBytes` for a type implementing
+/// `LmdbRawChecked`.
+///
+/// See `LmdbRawChecked` for details.
+#[macro_export]
+macro_rules! lmdb_raw_checked {
+    ($typ:ty) => {
+        impl $crate::traits::AsLmdbBytes for $typ {
+            fn as_lmdb_bytes(&self) -> &[u8] {
+                $crate::traits::checked_as_lmdb_bytes(self)
+            }
+        }
+
+        impl $crate::traits::FromLmdbBytes for $typ {
+            fn from_lmdb_bytes(bytes: &[u8])
+                               -> ::std::result::Result<
+                                   &Self, ::std::string::String> {
+                $crate::traits::checked_from_lmdb_bytes(bytes)
+            }
         }
     }
 }
 
-impl AsLmdbBytes for [char] {
-    fn as_lmdb_bytes(&self) -> &[u8] {
-        unsafe {
-            slice::from_raw_parts(
-                self.as_ptr() as *const u8,
-                self.len() * mem::size_of::<char>())
+/// Implements `LmdbRawChecked`, `AsLmdbBytes` and `FromLmdbBytes` for a
+/// fieldless enum.
+///
+/// The enum must be `Copy` and declared `#[repr]` with the integer type given
+/// to the macro, and every variant must be listed. Values are read by
+/// checking that the stored integer is the discriminant of one of the listed
+/// variants.
+///
+/// The syntax is `lmdb_enum!(MyEnum : u8 { VariantA, VariantB, VariantC })`.
+/// See `LmdbRawChecked` for a full example.
+#[macro_export]
+macro_rules! lmdb_enum {
+    ($typ:ident : $repr:ident { $($variant:ident),+ $(,)* }) => {
+        unsafe impl $crate::traits::LmdbRawChecked for $typ {
+            fn reported_type() -> ::std::string::String {
+                ::std::borrow::ToOwned::to_owned(stringify!($typ))
+            }
+
+            fn check_lmdb_bytes(bytes: &[u8])
+                                -> ::std::result::Result<
+                                    (), ::std::string::String> {
+                let mut raw = [0u8; ::std::mem::size_of::<$repr>()];
+                raw.copy_from_slice(bytes);
+                let value = $repr::from_ne_bytes(raw);
+                if $(value == $typ::$variant as $repr)||+ {
+                    Ok(())
+                } else {
+                    Err(format!("Invalid {} discriminant {}",
+                                stringify!($typ), value))
+                }
+            }
+        }
+
+        $crate::lmdb_raw_checked!($typ);
+
+        const _: () = assert!(
+            ::std::mem::size_of::<$typ>() ==
+                ::std::mem::size_of::<$repr>(),
+            concat!(stringify!($typ), " must be #[repr(", stringify!($repr),
+                    ")]"));
+    }
+}
+
+// Also implements `AsLmdbBytes` and `FromLmdbBytes` for slices, which
+// `lmdb_raw_checked!` cannot do outside this crate.
+macro_rules! raw_checked {
+    ($typ:ty) => {
+        lmdb_raw_checked!($typ);
+
+        impl AsLmdbBytes for [$typ] {
+            fn as_lmdb_bytes(&self) -> &[u8] {
+                unsafe {
+                    slice::from_raw_parts(
+                        self.as_ptr() as *const u8,
+                        self.len() * mem::size_of::<$typ>())
+                }
+            }
+        }
+
+        impl FromLmdbBytes for [$typ] {
+            fn from_lmdb_bytes(bytes: &[u8]) -> Result<&Self, String> {
+                try!(check_raw_slice::<$typ>(
+                    bytes, <$typ as LmdbRawChecked>::reported_type));
+                for chunk in bytes.chunks(mem::size_of::<$typ>()) {
+                    try!(<$typ as LmdbRawChecked>::check_lmdb_bytes(chunk));
+                }
+
+                unsafe {
+                    Ok(slice::from_raw_parts(
+                        bytes.as_ptr() as *const $typ,
+                        bytes.len() / mem::size_of::<$typ>()))
+                }
+            }
+        }
+    }
+}
+
+unsafe impl LmdbRawChecked for bool {
+    fn reported_type() -> String {
+        "bool".to_owned()
+    }
+
+    fn check_lmdb_bytes(bytes: &[u8]) -> Result<(), String> {
+        match bytes[0] {
+            0 | 1 => Ok(()),
+            b => Err(format!("Invalid bool value {}", b)),
         }
     }
 }
+raw_checked!(bool);
+unsafe impl LmdbOrdKey for bool {
+    fn ordered_by_bytes() -> bool { true }
+}
+
+unsafe impl LmdbRawChecked for char {
+    fn reported_type() -> String {
+        "char".to_owned()
+    }
+
+    fn check_lmdb_bytes(bytes: &[u8]) -> Result<(), String> {
+        let mut raw = [0u8;4];
+        raw.copy_from_slice(bytes);
+        let value = u32::from_ne_bytes(raw);
+        char::from_u32(value).map(|_| ()).ok_or_else(
+            || format!("Invalid char value 0x{:X}", value))
+    }
+}
+raw_checked!(char);
+unsafe impl LmdbOrdKey for char { }
+
+macro_rules! raw_nonzero {
+    ($typ:ident) => {
+        unsafe impl LmdbRawChecked for $typ {
+            fn reported_type() -> String {
+                stringify!($typ).to_owned()
+            }
+
+            fn check_lmdb_bytes(bytes: &[u8]) -> Result<(), String> {
+                if bytes.iter().all(|&b| 0 == b) {
+                    Err(concat!("Zero value for ", stringify!($typ))
+                        .to_owned())
+                } else {
+                    Ok(())
+                }
+            }
+        }
+        raw_checked!($typ);
+        unsafe impl LmdbOrdKey for $typ { }
+    }
+}
+
+raw_nonzero!(NonZeroU8);
+raw_nonzero!(NonZeroI8);
+raw_nonzero!(NonZeroU16);
+raw_nonzero!(NonZeroI16);
+raw_nonzero!(NonZeroU32);
+raw_nonzero!(NonZeroI32);
+raw_nonzero!(NonZeroU64);
+raw_nonzero!(NonZeroI64);
@@ -0,0 +1,286 @@
+// Copyright 2016 FullContact, Inc
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use std::result;
+
+use bigendian::BigEndianInt;
+use error::{Error, Result};
+
+/// A value which can be an element of a tuple encoded with `encode_key()`.
+///
+/// The encoding of each element is self-delimiting, and byte-by-byte
+/// comparison of encoded values gives the same result as comparing the
+/// original values:
+///
+/// - Integers are stored big-endian, with the sign bit flipped for signed
+///   types (as with `BigEndian`).
+///
+/// - `bool` is stored as one byte, `0` or `1`.
+///
+/// - Strings and byte strings have each `0x00` byte escaped as `0x00 0xFF`,
+///   and are terminated by `0x00 0x00`.
+pub trait KeyPart {
+    /// Appends the encoding of `self` to `out`.
+    fn encode_part(&self, out: &mut Vec<u8>);
+}
+
+/// Inverts `KeyPart`, decoding an element of a tuple.
+///
+/// Strings and byte strings can only be decoded into owned values, since
+/// their encoding may contain escapes.
+pub trait FromKeyPart : Sized {
+    /// Decodes one element from the front of `bytes`, advancing `bytes`
+    /// past it.
+    fn decode_part(bytes: &mut &[u8]) -> result::Result<Self, String>;
+}
+
+// Splits `n` bytes off the front of `bytes`.
+fn take<'a>(bytes: &mut &'a [u8], n: usize, what: &str)
+            -> result::Result<&'a [u8], String> {
+    if bytes.len() < n {
+        return Err(format!("Key truncated reading {}: need {} bytes, \
+                            have {}", what, n, bytes.len()));
+    }
+
+    let (head, tail) = bytes.split_at(n);
+    *bytes = tail;
+    Ok(head)
+}
+
+macro_rules! key_part_int {
+    ($typ:ident, $n:expr) => {
+        impl KeyPart for $typ {
+            fn encode_part(&self, out: &mut Vec<u8>) {
+                out.extend_from_slice(&self.to_key_bytes());
+            }
+        }
+
+        impl FromKeyPart for $typ {
+            fn decode_part(bytes: &mut &[u8])
+                           -> result::Result<Self, String> {
+                let mut buf = [0u8;$n];
+                buf.copy_from_slice(try!(take(bytes, $n, stringify!($typ))));
+                Ok($typ::from_key_bytes(buf))
+            }
+        }
+    }
+}
+
+key_part_int!(u16, 2);
+key_part_int!(i16, 2);
+key_part_int!(u32, 4);
+key_part_int!(i32, 4);
+key_part_int!(u64, 8);
+key_part_int!(i64, 8);
+
+impl KeyPart for u8 {
+    fn encode_part(&self, out: &mut Vec<u8>) {
+        out.push(*self);
+    }
+}
+
+impl FromKeyPart for u8 {
+    fn decode_part(bytes: &mut &[u8]) -> result::Result<Self, String> {
+        Ok(try!(take(bytes, 1, "u8"))[0])
+    }
+}
+
+impl KeyPart for i8 {
+    fn encode_part(&self, out: &mut Vec<u8>) {
+        out.push((*self as u8) ^ 0x80);
+    }
+}
+
+impl FromKeyPart for i8 {
+    fn decode_part(bytes: &mut &[u8]) -> result::Result<Self, String> {
+        Ok((try!(take(bytes, 1, "i8"))[0] ^ 0x80) as i8)
+    }
+}
+
+impl KeyPart for bool {
+    fn encode_part(&self, out: &mut Vec<u8>) {
+        out.push(*self as u8);
+    }
+}
+
+impl FromKeyPart for bool {
+    fn decode_part(bytes: &mut &[u8]) -> result::Result<Self, String> {
+        match try!(take(bytes, 1, "bool"))[0] {
+            0 => Ok(false),
+            1 => Ok(true),
+            b => Err(format!("Invalid bool in key: {}", b)),
+        }
+    }
+}
+
+impl KeyPart for [u8] {
+    fn encode_part(&self, out: &mut Vec<u8>) {
+        for &b in self {
+            out.push(b);
+            if 0 == b {
+                out.push(0xFF);
+            }
+        }
+        out.push(0);
+        out.push(0);
+    }
+}
+
+impl FromKeyPart for Vec<u8> {
+    fn decode_part(bytes: &mut &[u8]) -> result::Result<Self, String> {
+        let mut ret = Vec::new();
+        loop {
+            let b = try!(take(bytes, 1, "string"))[0];
+            if 0 != b {
+                ret.push(b);
+                continue;
+            }
+
+            match try!(take(bytes, 1, "string"))[0] {
+                0 => return Ok(ret),
+                0xFF => ret.push(0),
+                e => return Err(format!(
+                    "Invalid escape in key string: 0x00 0x{:02X}", e)),
+            }
+        }
+    }
+}
+
+impl KeyPart for str {
+    fn encode_part(&self, out: &mut Vec<u8>) {
+        self.as_bytes().encode_part(out)
+    }
+}
+
+impl FromKeyPart for String {
+    fn decode_part(bytes: &mut &[u8]) -> result::Result<Self, String> {
+        String::from_utf8(try!(Vec::<u8>::decode_part(bytes)))
+            .map_err(|e| format!("{}", e))
+    }
+}
+
+impl KeyPart for Vec<u8> {
+    fn encode_part(&self, out: &mut Vec<u8>) {
+        (**self).encode_part(out)
+    }
+}
+
+impl KeyPart for String {
+    fn encode_part(&self, out: &mut Vec<u8>) {
+        (**self).encode_part(out)
+    }
+}
+
+impl<T : KeyPart + ?Sized> KeyPart for &T {
+    fn encode_part(&self, out: &mut Vec<u8>) {
+        (**self).encode_part(out)
+    }
+}
+
+/// A tuple of `KeyPart`s which can be encoded with `encode_key()`.
+///
+/// This is implemented for tuples of up to 8 elements.
+pub trait KeyTuple {
+    /// Appends the encoding of each element of `self` to `out`.
+    fn encode_tuple(&self, out: &mut Vec<u8>);
+}
+
+/// A tuple of `FromKeyPart`s which can be decoded with `decode_key()`.
+///
+/// This is implemented for tuples of up to 8 elements.
+pub trait FromKeyTuple : Sized {
+    /// Decodes each element of the tuple from the front of `bytes`,
+    /// advancing `bytes` past them.
+    fn decode_tuple(bytes: &mut &[u8]) -> result::Result<Self, String>;
+}
+
+macro_rules! key_tuple {
+    ($($t:ident : $ix:tt),*) => {
+        impl<$($t : KeyPart),*> KeyTuple for ($($t,)*) {
+            fn encode_tuple(&self, out: &mut Vec<u8>) {
+                $(self.$ix.encode_part(out);)*
+            }
+        }
+
+        impl<$($t : FromKeyPart),*> FromKeyTuple for ($($t,)*) {
+            fn decode_tuple(bytes: &mut &[u8])
+                            -> result::Result<Self, String> {
+                Ok(($(try!($t::decode_part(bytes)),)*))
+            }
+        }
+    }
+}
+
+key_tuple!(A:0);
+key_tuple!(A:0, B:1);
+key_tuple!(A:0, B:1, C:2);
+key_tuple!(A:0, B:1, C:2, D:3);
+key_tuple!(A:0, B:1, C:2, D:3, E:4);
+key_tuple!(A:0, B:1, C:2, D:3, E:4, F:5);
+key_tuple!(A:0, B:1, C:2, D:3, E:4, F:5, G:6);
+key_tuple!(A:0, B:1, C:2, D:3, E:4, F:5, G:6, H:7);
+
+/// Encodes `key` so that byte-by-byte comparison of encoded tuples, as done
+/// by LMDB by default, matches the lexicographic order of the tuples.
+///
+/// The encoding of a tuple is the concatenation of the encodings of its
+/// elements (see `KeyPart`). This means that encoding only the first few
+/// elements of a key yields a prefix of the full encoded key, which can be
+/// passed to `ConstTransaction::prefix_iter()` to find all keys starting
+/// with those elements.
+///
+/// ## Example
+///
+/// ```
+/// # include!("src/example_helpers.rs");
+/// # fn main() {
+/// # let env = create_env();
+/// # let db = defdb(&env);
+/// use lmdb::{encode_key, decode_key};
+///
+/// let txn = lmdb::WriteTransaction::new(&env).unwrap();
+/// {
+///   let mut access = txn.access();
+///   let f = lmdb::put::Flags::empty();
+///   for &(tenant, ts, event) in &[(2u32, -5i64, "b"), (1, 100, "a"),
+///                                 (1, -1, "c"), (1, -1, "a\0b"),
+///                                 (12, 0, "")] {
+///     access.put(&db, &encode_key(&(tenant, ts, event)), "", f).unwrap();
+///   }
+///
+///   let keys = txn.prefix_keys::<[u8],_>(&db, &access, &encode_key(&(1u32,)))
+///     .unwrap().map(|k| decode_key::<(u32, i64, String)>(k.unwrap()).unwrap())
+///     .collect::<Vec<_>>();
+///   assert_eq!(vec![(1, -1, "a\0b".to_owned()),
+///                   (1, -1, "c".to_owned()),
+///                   (1, 100, "a".to_owned())], keys);
+/// }
+/// txn.commit().unwrap();
+/// # }
+/// ```
+pub fn encode_key<T : KeyTuple + ?Sized>(key: &T) -> Vec<u8> {
+    let mut out = Vec::new();
+    key.encode_tuple(&mut out);
+    out
+}
+
+/// Decodes a key produced by `encode_key()` back into a tuple.
+///
+/// ## Errors
+///
+/// Returns `Error::ValRejected` if `bytes` is not a valid encoding of `T`,
+/// including if there are bytes left over after decoding every element.
+pub fn decode_key<T : FromKeyTuple>(bytes: &[u8]) -> Result<T> {
+    let mut rest = bytes;
+    let ret = try!(T::decode_tuple(&mut rest).map_err(Error::ValRejected));
+    if !rest.is_empty() {
+        return Err(Error::ValRejected(format!(
+            "{} trailing bytes after key", rest.len())));
+    }
+    Ok(ret)
+}
@@ -6,21 +6,25 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
 
+use std::borrow::Borrow;
 use std::cell::Cell;
+use std::cmp::Ordering;
 use std::mem;
-use std::ops::{Deref, DerefMut};
+use std::ops::{Bound, Deref, DerefMut, RangeBounds};
 use std::ptr;
 use libc::c_uint;
 
 use ffi;
 use ffi2;
 
-use env::{self, Environment, Stat};
+use env::{self, Environment, EnvRef, Stat};
 use dbi::{db, Database};
 use error::{Error, Result};
 use mdb_vals::*;
 use traits::*;
 use cursor::{self, Cursor, StaleCursor};
+use iter::{CursorIter, MaybeOwned, RangeIter, PrefixIter, PrefixKeys};
+use iter::{DupIter, DupGroups, DupPages, AllDupPages};
 
 /// Flags used when calling the various `put` functions.
 pub mod put {
@@ -87,7 +91,7 @@ pub mod put {
             ///   access.put(&db, "Fruit", "Durian", lmdb::put::Flags::empty()).unwrap();
             ///
             ///   let mut cursor = txn.cursor(&db).unwrap();
-            ///   assert_eq!(Err(lmdb::Error::Code(lmdb::error::KEYEXIST)),
+            ///   assert_eq!(Err(lmdb::Error::KeyExist),
             ///              cursor.put(&mut access, "Fruit", "Durian",
             ///                         lmdb::put::NODUPDATA));
             ///   assert_eq!(("Fruit", "Durian"), cursor.get_current(&access).unwrap());
@@ -292,7 +296,9 @@ impl TxHandle {
 ///
 /// ## Lifetime
 ///
-/// A `ConstTransaction` must be strictly outlived by its `Environment`.
+/// A `ConstTransaction` must be strictly outlived by its `Environment`, unless
+/// it was created from an `Arc<Environment>`, in which case it keeps the
+/// environment alive itself and `'env` can be `'static`.
 ///
 /// `'env` is covariant: given two lifetimes `'x` and `'y` where `'x: 'y`, a
 /// `&ConstTransaction<'x>` will implicitly coerce to `&ConstTransaction<'y>`.
@@ -314,9 +320,18 @@ impl TxHandle {
 /// parameter, eg `&'x lmdb::ConstTransaction<'x>`.
 #[derive(Debug)]
 pub struct ConstTransaction<'env> {
-    env: &'env Environment,
+    // `tx` must be declared (and thus dropped) before `env`, since `env` may
+    // hold the last reference to the environment.
     tx: TxHandle,
+    env: EnvRef<'env>,
     has_yielded_accessor: Cell<bool>,
+    // Whether this transaction is counted as active on the environment. This
+    // is false for child transactions (which are covered by their parent) and
+    // reset transactions.
+    active: bool,
+    // Key of this transaction in the environment's reader tracker, if it is
+    // a tracked read transaction.
+    tracked: Option<usize>,
 }
 
 /// A read-only LMDB transaction.
@@ -433,21 +448,47 @@ pub struct ConstAccessor<'txn>(&'txn ConstTransaction<'txn>);
 pub struct WriteAccessor<'txn>(ConstAccessor<'txn>);
 
 impl<'env> ConstTransaction<'env> {
-    fn new<'outer: 'env>(env: &'env Environment,
+    fn new<'outer: 'env>(env: EnvRef<'env>,
                          parent: Option<&'env mut ConstTransaction<'outer>>,
                          flags: c_uint) -> Result<Self> {
-        let mut rawtx: *mut ffi::MDB_txn = ptr::null_mut();
+        let active = parent.is_none();
+        if active {
+            env::txn_begin(&env);
+        }
+        // Construct the transaction before actually beginning it so that the
+        // destructor takes care of the bookkeeping if that fails.
+        let mut this = ConstTransaction {
+            tx: TxHandle(ptr::null_mut()),
+            env: env,
+            has_yielded_accessor: Cell::new(false),
+            active: active,
+            tracked: None,
+        };
         unsafe {
             lmdb_call!(ffi::mdb_txn_begin(
-                env::env_ptr(env), parent.map_or(ptr::null_mut(), |p| p.tx.0),
-                flags, &mut rawtx));
+                env::env_ptr(&this.env),
+                parent.map_or(ptr::null_mut(), |p| p.tx.0),
+                flags, &mut this.tx.0));
         }
 
-        Ok(ConstTransaction {
-            env: env,
-            tx: TxHandle(rawtx),
-            has_yielded_accessor: Cell::new(false),
-        })
+        if active && 0 != flags & ffi::MDB_RDONLY {
+            this.track();
+        }
+        Ok(this)
+    }
+
+    fn track(&mut self) {
+        if let Some(tracker) = env::env_reader_tracker(&self.env) {
+            self.tracked = Some(tracker.track(self.id()));
+        }
+    }
+
+    fn untrack(&mut self) {
+        if let Some(key) = self.tracked.take() {
+            if let Some(tracker) = env::env_reader_tracker(&self.env) {
+                tracker.untrack(key);
+            }
+        }
     }
 
     /// Returns an accessor used to manipulate data in this transaction.
@@ -486,7 +527,7 @@ impl<'env> ConstTransaction<'env> {
     #[inline]
     pub fn cursor<'txn, 'db>(&'txn self, db: &'db Database)
                              -> Result<Cursor<'txn,'db>> {
-        try!(db.assert_same_env(self.env));
+        try!(db.assert_same_env(&self.env));
 
         let mut raw: *mut ffi::MDB_cursor = ptr::null_mut();
         unsafe {
@@ -496,6 +537,431 @@ impl<'env> ConstTransaction<'env> {
         Ok(unsafe { cursor::create_cursor(raw, self) })
     }
 
+    /// Returns an iterator over the items in `db` whose keys fall within
+    /// `range`.
+    ///
+    /// `range` may be any kind of range (`a..b`, `a..=b`, `a..`, `..b`,
+    /// `..`, or a pair of `Bound`s) whose bounds can be borrowed as `K`.
+    /// Keys are compared using the database's own ordering, including any
+    /// set with `DatabaseOptions::sort_keys_as`.
+    ///
+    /// This opens a new cursor for the iterator. See `RangeIter` for
+    /// details.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// # include!("src/example_helpers.rs");
+    /// # fn main() {
+    /// # let env = create_env();
+    /// use lmdb::Unaligned as U;
+    ///
+    /// // Sorted by the `Ord` of `i32`, not by the bytes of the keys
+    /// let db = lmdb::Database::open(
+    ///   &env, Some("example"),
+    ///   &lmdb::DatabaseOptions::create_map::<U<i32>, str>()).unwrap();
+    /// let txn = lmdb::WriteTransaction::new(&env).unwrap();
+    /// {
+    ///   let mut access = txn.access();
+    ///   let f = lmdb::put::Flags::empty();
+    ///   for &k in &[-10, -1, 0, 3, 7] {
+    ///     access.put(&db, &U::new(k), "", f).unwrap();
+    ///   }
+    ///
+    ///   let keys = |r: lmdb::RangeIter<U<i32>, str>| r
+    ///     .map(|kv| kv.unwrap().0.get()).collect::<Vec<_>>();
+    ///   assert_eq!(vec![-1, 0, 3],
+    ///              keys(txn.range(&db, &access, U::new(-5)..U::new(7))
+    ///                   .unwrap()));
+    ///   assert_eq!(vec![-1, 0, 3, 7],
+    ///              keys(txn.range(&db, &access, U::new(-5)..=U::new(7))
+    ///                   .unwrap()));
+    ///   assert_eq!(vec![-10, -1],
+    ///              keys(txn.range(&db, &access, ..U::new(0)).unwrap()));
+    ///   assert_eq!(vec![3, 7],
+    ///              keys(txn.range(&db, &access, U::new(3)..).unwrap()));
+    /// }
+    /// txn.commit().unwrap();
+    /// # }
+    /// ```
+    ///
+    /// Bounds need only be borrowable as the key type:
+    ///
+    /// ```
+    /// # include!("src/example_helpers.rs");
+    /// # fn main() {
+    /// # let env = create_env();
+    /// # let db = defdb(&env);
+    /// let txn = lmdb::WriteTransaction::new(&env).unwrap();
+    /// {
+    ///   let mut access = txn.access();
+    ///   let f = lmdb::put::Flags::empty();
+    ///   access.put(&db, "apple", "red", f).unwrap();
+    ///   access.put(&db, "banana", "yellow", f).unwrap();
+    ///   access.put(&db, "mango", "orange", f).unwrap();
+    ///
+    ///   let items = txn.range(&db, &access, "b".."m")
+    ///     .unwrap().collect::<lmdb::Result<Vec<(&str,&str)>>>().unwrap();
+    ///   assert_eq!(vec![("banana", "yellow")], items);
+    /// }
+    /// txn.commit().unwrap();
+    /// # }
+    /// ```
+    ///
+    /// An empty key sorts before every key, since LMDB does not allow empty
+    /// keys:
+    ///
+    /// ```
+    /// # include!("src/example_helpers.rs");
+    /// # fn main() {
+    /// # let env = create_env();
+    /// # let db = defdb(&env);
+    /// let txn = lmdb::WriteTransaction::new(&env).unwrap();
+    /// {
+    ///   let mut access = txn.access();
+    ///   let f = lmdb::put::Flags::empty();
+    ///   access.put(&db, "apple", "red", f).unwrap();
+    ///   access.put(&db, "banana", "yellow", f).unwrap();
+    ///
+    ///   let mut all = txn.range::<str,str,_,_>(&db, &access, "".."z")
+    ///     .unwrap();
+    ///   assert_eq!(("apple", "red"), all.next().unwrap().unwrap());
+    ///   assert_eq!(("banana", "yellow"), all.next_back().unwrap().unwrap());
+    ///   assert!(all.next().is_none());
+    ///
+    ///   let mut none = txn.range::<str,str,_,_>(&db, &access, ..="")
+    ///     .unwrap();
+    ///   assert!(none.next().is_none());
+    ///   assert!(none.next_back().is_none());
+    /// }
+    /// txn.commit().unwrap();
+    /// # }
+    /// ```
+    #[inline]
+    pub fn range<'txn, 'db, 'access,
+                 K : AsLmdbBytes + FromLmdbBytes + ?Sized,
+                 V : AsLmdbBytes + FromLmdbBytes + ?Sized,
+                 B : Borrow<K> + ?Sized, R : RangeBounds<B>>
+        (&'txn self, db: &'db Database, access: &'access ConstAccessor<'txn>,
+         range: R) -> Result<RangeIter<'access, 'txn, 'db, K, V>>
+    {
+        Ok(RangeIter::new(try!(self.cursor(db)), access, range))
+    }
+
+    /// Returns an iterator over all items in `db` in reverse order, starting
+    /// from the last.
+    ///
+    /// This is a `CursorIter` built on `Cursor::last` and `Cursor::prev`,
+    /// using a new cursor. To iterate a range of keys in reverse, use
+    /// `range(..).rev()` instead.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// # include!("src/example_helpers.rs");
+    /// # fn main() {
+    /// # let env = create_env();
+    /// use lmdb::Unaligned as U;
+    ///
+    /// let db = lmdb::Database::open(
+    ///   &env, Some("example"),
+    ///   &lmdb::DatabaseOptions::create_map::<U<u64>, str>()).unwrap();
+    /// let txn = lmdb::WriteTransaction::new(&env).unwrap();
+    /// {
+    ///   let mut access = txn.access();
+    ///   let f = lmdb::put::Flags::empty();
+    ///   access.put(&db, &U::new(1_000u64), "started", f).unwrap();
+    ///   access.put(&db, &U::new(1_500u64), "working", f).unwrap();
+    ///   access.put(&db, &U::new(2_000u64), "finished", f).unwrap();
+    ///
+    ///   // The latest two events
+    ///   let latest = txn.rev_iter::<U<u64>, str>(&db, &access).unwrap()
+    ///     .take(2).map(|kv| kv.unwrap().1).collect::<Vec<_>>();
+    ///   assert_eq!(vec!["finished", "working"], latest);
+    ///
+    ///   // The same, but only for events before 2000
+    ///   let latest = txn.range::<U<u64>, str, _, _>(
+    ///       &db, &access, ..U::new(2_000)).unwrap()
+    ///     .rev().take(2).map(|kv| kv.unwrap().1).collect::<Vec<_>>();
+    ///   assert_eq!(vec!["working", "started"], latest);
+    /// }
+    /// txn.commit().unwrap();
+    /// # }
+    /// ```
+    #[inline]
+    pub fn rev_iter<'txn, 'db, 'access,
+                    K : FromLmdbBytes + ?Sized, V : FromLmdbBytes + ?Sized>
+        (&'txn self, db: &'db Database, access: &'access ConstAccessor<'txn>)
+         -> Result<CursorIter<'access, 'access, 'txn, 'db,
+                              (&'access K, &'access V)>>
+    {
+        CursorIter::new(MaybeOwned::Owned(try!(self.cursor(db))), access,
+                        |c, a| c.last(a), Cursor::prev::<K,V>)
+    }
+
+    /// Returns an iterator over the items in `db` whose keys begin with
+    /// `prefix`.
+    ///
+    /// The prefix is compared byte-wise, so this is only meaningful on
+    /// databases using the default key ordering. See `PrefixIter` for
+    /// details.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// # include!("src/example_helpers.rs");
+    /// # fn main() {
+    /// # let env = create_env();
+    /// # let db = dupdb(&env);
+    /// let txn = lmdb::WriteTransaction::new(&env).unwrap();
+    /// {
+    ///   let mut access = txn.access();
+    ///   let f = lmdb::put::Flags::empty();
+    ///   access.put(&db, "user/1/name", "Alice", f).unwrap();
+    ///   access.put(&db, "user/12/name", "Carol", f).unwrap();
+    ///   access.put(&db, "user/2/name", "Bob", f).unwrap();
+    ///   access.put(&db, "user/1/tag", "admin", f).unwrap();
+    ///   access.put(&db, "user/1/tag", "staff", f).unwrap();
+    ///
+    ///   let items = txn.prefix_iter(&db, &access, "user/1/").unwrap()
+    ///     .collect::<lmdb::Result<Vec<(&str,&str)>>>().unwrap();
+    ///   assert_eq!(vec![("user/1/name", "Alice"),
+    ///                   ("user/1/tag", "admin"),
+    ///                   ("user/1/tag", "staff")], items);
+    ///
+    ///   let keys = txn.prefix_keys(&db, &access, "user/1").unwrap()
+    ///     .collect::<lmdb::Result<Vec<&str>>>().unwrap();
+    ///   assert_eq!(vec!["user/1/name", "user/1/tag", "user/12/name"], keys);
+    ///
+    ///   assert!(txn.prefix_keys::<str,str>(&db, &access, "group/").unwrap()
+    ///           .next().is_none());
+    ///
+    ///   // Every key begins with the empty prefix
+    ///   assert_eq!(5, txn.prefix_iter::<str,str,_>(&db, &access, "").unwrap()
+    ///              .collect::<lmdb::Result<Vec<_>>>().unwrap().len());
+    ///   assert_eq!(4, txn.prefix_keys::<str,_>(&db, &access, "").unwrap()
+    ///              .collect::<lmdb::Result<Vec<_>>>().unwrap().len());
+    /// }
+    /// txn.commit().unwrap();
+    /// # }
+    /// ```
+    #[inline]
+    pub fn prefix_iter<'txn, 'db, 'access,
+                       K : FromLmdbBytes + ?Sized, V : FromLmdbBytes + ?Sized,
+                       P : AsLmdbBytes + ?Sized>
+        (&'txn self, db: &'db Database, access: &'access ConstAccessor<'txn>,
+         prefix: &P) -> Result<PrefixIter<'access, 'txn, 'db, K, V>>
+    {
+        Ok(PrefixIter::new(try!(self.cursor(db)), access, prefix))
+    }
+
+    /// Returns an iterator over the distinct keys in `db` which begin with
+    /// `prefix`.
+    ///
+    /// This is the keys-only counterpart to `prefix_iter`; values are never
+    /// converted. See `PrefixKeys` for details.
+    #[inline]
+    pub fn prefix_keys<'txn, 'db, 'access,
+                       K : FromLmdbBytes + ?Sized, P : AsLmdbBytes + ?Sized>
+        (&'txn self, db: &'db Database, access: &'access ConstAccessor<'txn>,
+         prefix: &P) -> Result<PrefixKeys<'access, 'txn, 'db, K>>
+    {
+        Ok(PrefixKeys::new(try!(self.cursor(db)), access, prefix))
+    }
+
+    /// Returns an iterator over the values of `key` in `db`.
+    ///
+    /// This is mainly useful for `DUPSORT` databases, where it produces every
+    /// value of the key in sorted order. If `key` does not exist, the
+    /// iterator is empty. See `DupIter` for details.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// # include!("src/example_helpers.rs");
+    /// # fn main() {
+    /// # let env = create_env();
+    /// # let db = dupdb(&env);
+    /// let txn = lmdb::WriteTransaction::new(&env).unwrap();
+    /// {
+    ///   let mut access = txn.access();
+    ///   let f = lmdb::put::Flags::empty();
+    ///   access.put(&db, "Fruit", "Apple", f).unwrap();
+    ///   access.put(&db, "Fruit", "Orange", f).unwrap();
+    ///   access.put(&db, "Animal", "Badger", f).unwrap();
+    ///
+    ///   let fruit = txn.iter_dup_of(&db, &access, "Fruit").unwrap()
+    ///     .collect::<lmdb::Result<Vec<&str>>>().unwrap();
+    ///   assert_eq!(vec!["Apple", "Orange"], fruit);
+    ///   assert!(txn.iter_dup_of::<str,str>(&db, &access, "Veggie").unwrap()
+    ///           .next().is_none());
+    /// }
+    /// txn.commit().unwrap();
+    /// # }
+    /// ```
+    ///
+    /// On `DUPFIXED` databases, values are read a page at a time:
+    ///
+    /// ```
+    /// # include!("src/example_helpers.rs");
+    /// # fn main() {
+    /// # let env = create_env();
+    /// # let db = dupfixeddb(&env);
+    /// let txn = lmdb::WriteTransaction::new(&env).unwrap();
+    /// {
+    ///   let mut access = txn.access();
+    ///   let f = lmdb::put::Flags::empty();
+    ///   for i in 0u32..10000 {
+    ///     access.put(&db, "posting", &i.to_be_bytes(), f).unwrap();
+    ///   }
+    ///
+    ///   let mut n = 0u32;
+    ///   for v in txn.iter_dup_of::<str,[u8]>(&db, &access, "posting")
+    ///     .unwrap()
+    ///   {
+    ///     assert_eq!(&n.to_be_bytes()[..], v.unwrap());
+    ///     n += 1;
+    ///   }
+    ///   assert_eq!(10000, n);
+    /// }
+    /// txn.commit().unwrap();
+    /// # }
+    /// ```
+    #[inline]
+    pub fn iter_dup_of<'txn, 'db, 'access,
+                       K : AsLmdbBytes + ?Sized, V : FromLmdbBytes + ?Sized>
+        (&'txn self, db: &'db Database, access: &'access ConstAccessor<'txn>,
+         key: &K) -> Result<DupIter<'access, 'txn, 'db, V>>
+    {
+        Ok(DupIter::new(try!(self.cursor(db)), access, key))
+    }
+
+    /// Returns an iterator over the distinct keys in `db`, each with an
+    /// iterator over its values.
+    ///
+    /// See `DupGroups` for details.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// # include!("src/example_helpers.rs");
+    /// # fn main() {
+    /// # let env = create_env();
+    /// # let db = dupdb(&env);
+    /// let txn = lmdb::WriteTransaction::new(&env).unwrap();
+    /// {
+    ///   let mut access = txn.access();
+    ///   let f = lmdb::put::Flags::empty();
+    ///   access.put(&db, "Fruit", "Apple", f).unwrap();
+    ///   access.put(&db, "Fruit", "Orange", f).unwrap();
+    ///   access.put(&db, "Animal", "Badger", f).unwrap();
+    ///   access.put(&db, "Veggie", "Carrot", f).unwrap();
+    ///
+    ///   let mut groups = Vec::new();
+    ///   for group in txn.iter_dup_groups(&db, &access).unwrap() {
+    ///     let (key, values) = group.unwrap();
+    ///     groups.push((key, values.collect::<lmdb::Result<Vec<&str>>>()
+    ///                          .unwrap()));
+    ///   }
+    ///   assert_eq!(vec![("Animal", vec!["Badger"]),
+    ///                   ("Fruit", vec!["Apple", "Orange"]),
+    ///                   ("Veggie", vec!["Carrot"])], groups);
+    /// }
+    /// txn.commit().unwrap();
+    /// # }
+    /// ```
+    #[inline]
+    pub fn iter_dup_groups<'txn, 'db, 'access,
+                           K : FromLmdbBytes + ?Sized,
+                           V : FromLmdbBytes + ?Sized>
+        (&'txn self, db: &'db Database, access: &'access ConstAccessor<'txn>)
+         -> Result<DupGroups<'access, 'txn, 'db, K, V>>
+    {
+        Ok(DupGroups::new(try!(self.cursor(db)), access))
+    }
+
+    /// Returns an iterator over the values of `key` in the `DUPFIXED`
+    /// database `db`, producing a page of values at a time.
+    ///
+    /// See `DupPages` for details.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// # include!("src/example_helpers.rs");
+    /// # fn main() {
+    /// # let env = create_env();
+    /// # let db = dupfixeddb(&env);
+    /// let txn = lmdb::WriteTransaction::new(&env).unwrap();
+    /// {
+    ///   let mut access = txn.access();
+    ///   let f = lmdb::put::Flags::empty();
+    ///   for i in 0u32..10000 {
+    ///     access.put(&db, "posting", &i.to_be_bytes(), f).unwrap();
+    ///   }
+    ///
+    ///   let mut total = 0;
+    ///   let mut pages = 0;
+    ///   for page in txn.iter_dup_pages_of::<str,[u8;4]>(
+    ///     &db, &access, "posting").unwrap()
+    ///   {
+    ///     let page = page.unwrap();
+    ///     assert_eq!(total, u32::from_be_bytes(page[0]));
+    ///     total += page.len() as u32;
+    ///     pages += 1;
+    ///   }
+    ///   assert_eq!(10000, total);
+    ///   assert!(pages < 100);
+    /// }
+    /// txn.commit().unwrap();
+    /// # }
+    /// ```
+    #[inline]
+    pub fn iter_dup_pages_of<'txn, 'db, 'access,
+                             K : AsLmdbBytes + ?Sized, V : LmdbRaw>
+        (&'txn self, db: &'db Database, access: &'access ConstAccessor<'txn>,
+         key: &K) -> Result<DupPages<'access, 'txn, 'db, V>>
+    {
+        Ok(DupPages::new(try!(self.cursor(db)), access, key))
+    }
+
+    /// Returns an iterator over the values of every key in the `DUPFIXED`
+    /// database `db`, producing each key with a page of its values at a
+    /// time.
+    ///
+    /// See `AllDupPages` for details.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// # include!("src/example_helpers.rs");
+    /// # fn main() {
+    /// # let env = create_env();
+    /// # let db = dupfixeddb(&env);
+    /// let txn = lmdb::WriteTransaction::new(&env).unwrap();
+    /// {
+    ///   let mut access = txn.access();
+    ///   let f = lmdb::put::Flags::empty();
+    ///   access.put(&db, "a", &1u8, f).unwrap();
+    ///   access.put(&db, "a", &2u8, f).unwrap();
+    ///   access.put(&db, "b", &3u8, f).unwrap();
+    ///
+    ///   let pages = txn.iter_dup_pages::<str,u8>(&db, &access).unwrap()
+    ///     .collect::<lmdb::Result<Vec<_>>>().unwrap();
+    ///   assert_eq!(vec![("a", &[1u8, 2][..]), ("b", &[3u8][..])], pages);
+    /// }
+    /// txn.commit().unwrap();
+    /// # }
+    /// ```
+    #[inline]
+    pub fn iter_dup_pages<'txn, 'db, 'access,
+                          K : FromLmdbBytes + ?Sized, V : LmdbRaw>
+        (&'txn self, db: &'db Database, access: &'access ConstAccessor<'txn>)
+         -> Result<AllDupPages<'access, 'txn, 'db, K, V>>
+    {
+        Ok(AllDupPages::new(try!(self.cursor(db)), access))
+    }
+
     /// Returns the internal id of this transaction.
     pub fn id(&self) -> usize {
         unsafe {
@@ -505,7 +971,7 @@ impl<'env> ConstTransaction<'env> {
 
     /// Retrieves statistics for a database.
     pub fn db_stat(&self, db: &Database) -> Result<Stat> {
-        try!(db.assert_same_env(self.env));
+        try!(db.assert_same_env(&self.env));
 
         unsafe {
             let mut raw: ffi::MDB_stat = mem::zeroed();
@@ -516,7 +982,7 @@ impl<'env> ConstTransaction<'env> {
 
     /// Retrieve the DB flags for a database handle.
     pub fn db_flags(&self, db: &Database) -> Result<db::Flags> {
-        try!(db.assert_same_env(self.env));
+        try!(db.assert_same_env(&self.env));
 
         let mut raw: c_uint = 0;
         unsafe {
@@ -538,6 +1004,28 @@ impl<'env> ConstTransaction<'env> {
     }
 }
 
+impl<'env> Drop for ConstTransaction<'env> {
+    fn drop(&mut self) {
+        // Make sure the transaction is actually gone before it stops being
+        // counted as active.
+        drop(mem::replace(&mut self.tx, TxHandle(ptr::null_mut())));
+        self.untrack();
+        if self.active {
+            env::txn_end(&self.env);
+        }
+    }
+}
+
+// Internally used by other parts of the crate
+pub fn txn_ptr(txn: &ConstTransaction) -> *mut ffi::MDB_txn {
+    txn.tx.0
+}
+
+// Internally used by other parts of the crate
+pub fn txn_env<'txn>(txn: &'txn ConstTransaction) -> &'txn Environment {
+    &txn.env
+}
+
 // Internally used by other parts of the crate
 #[inline]
 pub fn assert_sensible_cursor(access: &ConstAccessor, cursor: &Cursor)
@@ -569,9 +1057,13 @@ impl<'env> ReadTransaction<'env> {
     /// transaction at a time. If `NOTLS` is in use, this does not apply to
     /// read-only transactions. Attempting to open a read-only transaction
     /// while the current thread holds a read-write transaction will deadlock.
-    pub fn new(env: &'env Environment) -> Result<Self> {
+    ///
+    /// `env` may be either an `&Environment` or an `Arc<Environment>`; in the
+    /// latter case, the transaction shares ownership of the environment. See
+    /// `EnvRef`.
+    pub fn new<E : Into<EnvRef<'env>>>(env: E) -> Result<Self> {
         Ok(ReadTransaction(try!(ConstTransaction::new(
-            env, None, ffi::MDB_RDONLY))))
+            env.into(), None, ffi::MDB_RDONLY))))
     }
 
     /// Dissociates the given cursor from this transaction and its database,
@@ -613,7 +1105,7 @@ impl<'env> ReadTransaction<'env> {
                                    -> Result<StaleCursor<'db>>
     where 'env: 'db {
         try!(self.assert_sensible_cursor(&cursor));
-        Ok(cursor::to_stale(cursor, self.env))
+        Ok(cursor::to_stale(cursor, self.env.clone()))
     }
 
     /// Associates a saved read-only with this transaction.
@@ -622,7 +1114,7 @@ impl<'env> ReadTransaction<'env> {
     /// the same database that it was previously.
     pub fn assoc_cursor<'txn,'db>(&'txn self, cursor: StaleCursor<'db>)
                                   -> Result<Cursor<'txn,'db>> {
-        if self.env as *const Environment !=
+        if &*self.env as *const Environment !=
             cursor::env_ref(&cursor) as *const Environment
         {
             return Err(Error::Mismatch)
@@ -671,17 +1163,39 @@ impl<'env> ReadTransaction<'env> {
     /// }
     /// # }
     /// ```
-    pub fn reset(self) -> ResetTransaction<'env> {
+    pub fn reset(mut self) -> ResetTransaction<'env> {
         unsafe { ffi::mdb_txn_reset(self.0.tx.0); }
+        self.0.untrack();
+        // A reset transaction does not hold a snapshot, and so does not need
+        // to hold up resizing the map.
+        env::txn_end(&self.0.env);
+        self.0.active = false;
         ResetTransaction(self)
     }
+
+    /// Sets the label identifying this transaction in the results of
+    /// `Environment::tracked_readers()` and `Environment::old_readers()`.
+    ///
+    /// This does nothing unless reader tracking is enabled with
+    /// `EnvBuilder::set_reader_tracking()`. The label is cleared when the
+    /// transaction is reset.
+    pub fn set_label(&self, label: &str) {
+        if let (Some(tracker), Some(key)) =
+            (env::env_reader_tracker(&self.0.env), self.0.tracked)
+        {
+            tracker.set_label(key, label);
+        }
+    }
 }
 
 impl<'env> ResetTransaction<'env> {
     /// Renews this read-only transaction, making it available for more
     /// reading.
-    pub fn renew(self) -> Result<ReadTransaction<'env>> {
+    pub fn renew(mut self) -> Result<ReadTransaction<'env>> {
+        env::txn_begin(&(self.0).0.env);
+        (self.0).0.active = true;
         unsafe { lmdb_call!(ffi::mdb_txn_renew((self.0).0.tx.0)); }
+        (self.0).0.track();
         self.0.has_yielded_accessor.set(false);
         Ok(self.0)
     }
@@ -712,8 +1226,13 @@ impl<'env> WriteTransaction<'env> {
     /// read-write transaction at a time (even if `NOTLS` is in use --- trying
     /// to start two top-level read-write transactions on the same thread will
     /// deadlock).
-    pub fn new(env: &'env Environment) -> Result<Self> {
-        Ok(WriteTransaction(try!(ConstTransaction::new(env, None, 0))))
+    ///
+    /// `env` may be either an `&Environment` or an `Arc<Environment>`; in the
+    /// latter case, the transaction shares ownership of the environment. See
+    /// `EnvRef`.
+    pub fn new<E : Into<EnvRef<'env>>>(env: E) -> Result<Self> {
+        Ok(WriteTransaction(try!(ConstTransaction::new(
+            env.into(), None, 0))))
     }
 
     /// Opens a new, read-write transaction as a child transaction of the given
@@ -795,7 +1314,7 @@ impl<'env> WriteTransaction<'env> {
         // rules ensure that they've destroyed the old one.
         self.has_yielded_accessor.set(false);
 
-        let env = self.0.env;
+        let env = self.0.env.clone();
         Ok(WriteTransaction(try!(ConstTransaction::new(
             env, Some(&mut*self), 0))))
     }
@@ -855,12 +1374,79 @@ impl<'txn> ConstAccessor<'txn> {
         from_val(self, &out_val)
     }
 
+    /// Like `get()`, but decodes the value with `FromLmdbBytesOwned`, so that
+    /// the result can outlive the accessor.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// # include!("src/example_helpers.rs");
+    /// # fn main() {
+    /// # let env = create_env();
+    /// # let db = defdb(&env);
+    /// use std::borrow::Cow;
+    ///
+    /// let name: String;
+    /// let scores: Vec<u32>;
+    /// {
+    ///   let txn = lmdb::WriteTransaction::new(&env).unwrap();
+    ///   let mut access = txn.access();
+    ///   let f = lmdb::put::Flags::empty();
+    ///   access.put(&db, "name", "Rīga", f).unwrap();
+    ///   access.put(&db, "scores", &[10u32, 20, 30][..], f).unwrap();
+    ///
+    ///   name = access.get_owned(&db, "name").unwrap();
+    ///   // There is no `get::<str,[u32]>()`, since the value may be misaligned
+    ///   scores = access.get_owned(&db, "scores").unwrap();
+    ///   // `Cow<str>` borrows from the database
+    ///   let borrowed: Cow<str> = access.get_owned(&db, "name").unwrap();
+    ///   assert!(match borrowed { Cow::Borrowed(_) => true, _ => false });
+    ///
+    ///   // Cursors can produce owned values as well, including through
+    ///   // `CursorIter`
+    ///   let mut cursor = txn.cursor(&db).unwrap();
+    ///   let keys = lmdb::CursorIter::new(
+    ///     lmdb::MaybeOwned::Borrowed(&mut cursor), &*access,
+    ///     |c, a| c.first_owned(a),
+    ///     lmdb::Cursor::next_owned::<String,Box<[u8]>>)
+    ///     .unwrap().map(|r| r.unwrap().0).collect::<Vec<_>>();
+    ///   assert_eq!(vec!["name", "scores"], keys);
+    /// }
+    /// assert_eq!("Rīga", name);
+    /// assert_eq!(vec![10, 20, 30], scores);
+    /// # }
+    /// ```
+    #[inline]
+    pub fn get_owned<'access, K : AsLmdbBytes + ?Sized,
+                     V : FromLmdbBytesOwned<'access>>(
+        &'access self, db: &Database, key: &K) -> Result<V>
+    {
+        try!(db.assert_same_env(self.env()));
+
+        let mut mv_key = as_val(key);
+        let mut out_val = EMPTY_VAL;
+        unsafe {
+            lmdb_call!(ffi::mdb_get(
+                self.txptr(), db.dbi(), &mut mv_key, &mut out_val));
+        }
+
+        from_val_owned(self, &out_val)
+    }
+
     fn txptr(&self) -> *mut ffi::MDB_txn {
         self.0.tx.0
     }
 
     fn env(&self) -> &Environment {
-        self.0.env
+        &self.0.env
+    }
+
+    // Attaches context to `error` from `operation` on `key` in `db`.
+    fn context<K : AsLmdbBytes + ?Sized>(&self, error: Error,
+                                         operation: &'static str,
+                                         db: &Database, key: &K) -> Error {
+        env::dbi_error_context(self.env(), error, operation, db.dbi(),
+                               Some(key.as_lmdb_bytes()))
     }
 }
 
@@ -891,7 +1477,8 @@ impl<'txn> WriteAccessor<'txn> {
         unsafe {
             lmdb_call!(ffi::mdb_put(
                 self.txptr(), db.dbi(), &mut mv_key, &mut mv_val,
-                flags.bits()));
+                flags.bits()),
+                |err| self.context(err, "mdb_put", db, key));
         }
         Ok(())
     }
@@ -1031,7 +1618,8 @@ impl<'txn> WriteAccessor<'txn> {
         out_val.mv_size = size;
         lmdb_call!(ffi::mdb_put(
             self.txptr(), db.dbi(), &mut mv_key, &mut out_val,
-            flags.bits() | ffi::MDB_RESERVE));
+            flags.bits() | ffi::MDB_RESERVE),
+            |err| self.context(err, "mdb_put", db, key));
 
         Ok(from_reserved(self, &out_val))
     }
@@ -1074,7 +1662,8 @@ impl<'txn> WriteAccessor<'txn> {
         let mut mv_key = as_val(key);
         unsafe {
             lmdb_call!(ffi::mdb_del(
-                self.txptr(), db.dbi(), &mut mv_key, ptr::null_mut()));
+                self.txptr(), db.dbi(), &mut mv_key, ptr::null_mut()),
+                |err| self.context(err, "mdb_del", db, key));
         }
 
         Ok(())
@@ -1121,12 +1710,310 @@ impl<'txn> WriteAccessor<'txn> {
         let mut mv_val = as_val(val);
         unsafe {
             lmdb_call!(ffi::mdb_del(
-                self.txptr(), db.dbi(), &mut mv_key, &mut mv_val));
+                self.txptr(), db.dbi(), &mut mv_key, &mut mv_val),
+                |err| self.context(err, "mdb_del", db, key));
         }
 
         Ok(())
     }
 
+    /// Deletes every item in the database whose key falls within `range`,
+    /// returning the number of items deleted.
+    ///
+    /// `range` is interpreted as with `ConstTransaction::range`, including
+    /// the use of the database's own key ordering and the treatment of empty
+    /// keys as sorting before every key. On `DUPSORT` databases,
+    /// every value of each key in the range is deleted, as with `del_key`,
+    /// and each value counts as one item; use `del_item_range` to delete
+    /// individual values instead.
+    ///
+    /// Unlike `del_key`, it is not an error for the range to be empty.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// # include!("src/example_helpers.rs");
+    /// # fn main() {
+    /// # let env = create_env();
+    /// # let db = dupdb(&env);
+    /// let txn = lmdb::WriteTransaction::new(&env).unwrap();
+    /// {
+    ///   let mut access = txn.access();
+    ///   let f = lmdb::put::Flags::empty();
+    ///   access.put(&db, "Animal", "Badger", f).unwrap();
+    ///   access.put(&db, "Fruit", "Apple", f).unwrap();
+    ///   access.put(&db, "Fruit", "Orange", f).unwrap();
+    ///   access.put(&db, "Veggie", "Carrot", f).unwrap();
+    ///
+    ///   assert_eq!(2, access.del_range::<str,_,_>(&db, "B".."V").unwrap());
+    ///   assert_eq!(0, access.del_range::<str,_,_>(&db, "B".."V").unwrap());
+    ///   assert_eq!(2, txn.db_stat(&db).unwrap().entries);
+    ///
+    ///   // The empty key sorts before every key
+    ///   assert_eq!(0, access.del_range::<str,_,_>(&db, ..="").unwrap());
+    ///   assert_eq!(1, access.del_range::<str,_,_>(&db, "".."B").unwrap());
+    ///   assert_eq!(1, txn.db_stat(&db).unwrap().entries);
+    /// }
+    /// txn.commit().unwrap();
+    /// # }
+    /// ```
+    pub fn del_range<K : AsLmdbBytes + ?Sized, B : Borrow<K> + ?Sized,
+                     R : RangeBounds<B>>
+        (&mut self, db: &Database, range: R) -> Result<usize>
+    {
+        let is_empty = |b: &B| b.borrow().as_lmdb_bytes().is_empty();
+        match range.end_bound() {
+            Bound::Included(end) | Bound::Excluded(end) if is_empty(end) =>
+                return Ok(0),
+            _ => (),
+        }
+
+        let mut cursor = try!((self.0).0.cursor(db));
+        let found = match range.start_bound() {
+            // The empty key cannot be passed to `seek_range_k`, but no key
+            // sorts before it.
+            Bound::Unbounded =>
+                try!(cursor.first::<[u8],[u8]>(self).to_opt()).is_some(),
+            Bound::Included(start) | Bound::Excluded(start)
+            if is_empty(start) =>
+                try!(cursor.first::<[u8],[u8]>(self).to_opt()).is_some(),
+            Bound::Included(start) =>
+                try!(cursor.seek_range_k::<[u8],[u8]>(
+                    self, start.borrow().as_lmdb_bytes()).to_opt()).is_some(),
+            Bound::Excluded(start) => {
+                let start = start.borrow().as_lmdb_bytes();
+                match try!(cursor.seek_range_k::<[u8],[u8]>(self, start)
+                           .to_opt()) {
+                    Some((k, _)) if Ordering::Equal ==
+                        cursor::cmp_keys(&cursor, k, start) =>
+                        try!(cursor.next_nodup::<[u8],[u8]>(self).to_opt())
+                        .is_some(),
+                    found => found.is_some(),
+                }
+            },
+        };
+
+        let end = range.end_bound();
+        self.del_while(cursor, found, true, |cursor, key, _| {
+            let cmp = |end: &B| cursor::cmp_keys(
+                cursor, key, end.borrow().as_lmdb_bytes());
+            match end {
+                Bound::Unbounded => true,
+                Bound::Included(end) => Ordering::Greater != cmp(end),
+                Bound::Excluded(end) => Ordering::Less == cmp(end),
+            }
+        })
+    }
+
+    /// Deletes every item in the database whose key begins with `prefix`,
+    /// returning the number of items deleted.
+    ///
+    /// As with `ConstTransaction::prefix_iter`, the prefix is compared
+    /// byte-wise, so this is only meaningful on databases using the default
+    /// key ordering. `DUPSORT` databases are handled as with `del_range`.
+    ///
+    /// Every key begins with the empty prefix, so passing an empty prefix
+    /// deletes every item in the database.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// # include!("src/example_helpers.rs");
+    /// # fn main() {
+    /// # let env = create_env();
+    /// # let db = defdb(&env);
+    /// let txn = lmdb::WriteTransaction::new(&env).unwrap();
+    /// {
+    ///   let mut access = txn.access();
+    ///   let f = lmdb::put::Flags::empty();
+    ///   access.put(&db, "user/1/name", "Alice", f).unwrap();
+    ///   access.put(&db, "user/1/role", "admin", f).unwrap();
+    ///   access.put(&db, "user/2/name", "Bob", f).unwrap();
+    ///
+    ///   assert_eq!(2, access.del_prefix(&db, "user/1/").unwrap());
+    ///   assert_eq!("Bob", access.get::<str,str>(&db, "user/2/name").unwrap());
+    ///   assert_eq!(1, txn.db_stat(&db).unwrap().entries);
+    ///
+    ///   assert_eq!(1, access.del_prefix(&db, "").unwrap());
+    ///   assert_eq!(0, txn.db_stat(&db).unwrap().entries);
+    /// }
+    /// txn.commit().unwrap();
+    /// # }
+    /// ```
+    pub fn del_prefix<P : AsLmdbBytes + ?Sized>
+        (&mut self, db: &Database, prefix: &P) -> Result<usize>
+    {
+        let prefix = prefix.as_lmdb_bytes();
+        let mut cursor = try!((self.0).0.cursor(db));
+        let found = if prefix.is_empty() {
+            try!(cursor.first::<[u8],[u8]>(self).to_opt()).is_some()
+        } else {
+            try!(cursor.seek_range_k::<[u8],[u8]>(self, prefix)
+                 .to_opt()).is_some()
+        };
+        self.del_while(cursor, found, true,
+                       |_, key, _| key.starts_with(prefix))
+    }
+
+    /// Deletes every item in the database whose key/value pair falls within
+    /// `range`, returning the number of items deleted.
+    ///
+    /// This is to `del_range` what `del_item` is to `del_key`. The bounds of
+    /// `range` are key/value pairs, ordered by key and then, on `DUPSORT`
+    /// databases, by value, so the range may begin or end part way through
+    /// the values of a key. Each value is deleted individually. On other
+    /// databases, the values in the bounds are ignored.
+    ///
+    /// As with `del_range`, it is not an error for the range to be empty.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// # include!("src/example_helpers.rs");
+    /// # fn main() {
+    /// # let env = create_env();
+    /// # let db = dupdb(&env);
+    /// let txn = lmdb::WriteTransaction::new(&env).unwrap();
+    /// {
+    ///   let mut access = txn.access();
+    ///   let f = lmdb::put::Flags::empty();
+    ///   access.put(&db, "Animal", "Badger", f).unwrap();
+    ///   access.put(&db, "Fruit", "Apple", f).unwrap();
+    ///   access.put(&db, "Fruit", "Orange", f).unwrap();
+    ///   access.put(&db, "Fruit", "Pear", f).unwrap();
+    ///   access.put(&db, "Veggie", "Carrot", f).unwrap();
+    ///   access.put(&db, "Veggie", "Potato", f).unwrap();
+    ///
+    ///   // Only the values within the bounds are deleted from "Fruit" and
+    ///   // "Veggie"
+    ///   assert_eq!(3, access.del_item_range(
+    ///     &db, ("Fruit", "Banana")..("Veggie", "Potato")).unwrap());
+    ///   assert_eq!("Apple", access.get::<str,str>(&db, "Fruit").unwrap());
+    ///   assert_eq!("Potato", access.get::<str,str>(&db, "Veggie").unwrap());
+    ///   assert_eq!(3, txn.db_stat(&db).unwrap().entries);
+    ///
+    ///   // Excluded start bounds skip exactly the given item
+    ///   assert_eq!(1, access.del_item_range(
+    ///     &db, (std::ops::Bound::Excluded(("Animal", "Badger")),
+    ///           std::ops::Bound::Included(("Veggie", "A")))).unwrap());
+    ///   assert_eq!(2, txn.db_stat(&db).unwrap().entries);
+    /// }
+    /// txn.commit().unwrap();
+    /// # }
+    /// ```
+    pub fn del_item_range<'r, K : AsLmdbBytes + ?Sized + 'r,
+                          V : AsLmdbBytes + ?Sized + 'r,
+                          R : RangeBounds<(&'r K, &'r V)>>
+        (&mut self, db: &Database, range: R) -> Result<usize>
+    {
+        let mut cursor = try!((self.0).0.cursor(db));
+        let found = match range.start_bound() {
+            Bound::Unbounded =>
+                try!(cursor.first::<[u8],[u8]>(self).to_opt()).is_some(),
+            Bound::Included(&(key, val)) =>
+                try!(self.seek_item(&mut cursor, key.as_lmdb_bytes(),
+                                    val.as_lmdb_bytes())),
+            Bound::Excluded(&(key, val)) => {
+                let start = (key.as_lmdb_bytes(), val.as_lmdb_bytes());
+                if try!(self.seek_item(&mut cursor, start.0, start.1)) {
+                    let at_start = {
+                        let current = try!(
+                            cursor.get_current::<[u8],[u8]>(self));
+                        Ordering::Equal ==
+                            cursor::cmp_items(&cursor, current, start)
+                    };
+                    if at_start {
+                        try!(cursor.next::<[u8],[u8]>(self).to_opt())
+                            .is_some()
+                    } else {
+                        true
+                    }
+                } else {
+                    false
+                }
+            },
+        };
+
+        let end = range.end_bound();
+        self.del_while(cursor, found, false, |cursor, key, val| {
+            let cmp = |&(end_key, end_val): &(&K, &V)| cursor::cmp_items(
+                cursor, (key, val),
+                (end_key.as_lmdb_bytes(), end_val.as_lmdb_bytes()));
+            match end {
+                Bound::Unbounded => true,
+                Bound::Included(end) => Ordering::Greater != cmp(end),
+                Bound::Excluded(end) => Ordering::Less == cmp(end),
+            }
+        })
+    }
+
+    /// Positions `cursor` at the first item at or after `key` and `val`,
+    /// returning whether there is one.
+    fn seek_item(&self, cursor: &mut Cursor, key: &[u8], val: &[u8])
+                 -> Result<bool> {
+        // The empty key cannot be passed to LMDB, but sorts before every
+        // item.
+        if key.is_empty() {
+            return Ok(try!(cursor.first::<[u8],[u8]>(self).to_opt())
+                      .is_some());
+        }
+
+        // `MDB_GET_BOTH_RANGE` compares values, which LMDB can only do on
+        // `DUPSORT` databases.
+        let dupsort = cursor::db_flags(cursor).contains(db::DUPSORT);
+        if dupsort && try!(cursor.seek_k_nearest_v::<[u8],[u8]>(
+            self, key, val).to_opt()).is_some()
+        {
+            return Ok(true);
+        }
+
+        // Either `key` has no values at or after `val`, or values are not
+        // being compared, so start from the first key at or after `key`.
+        match try!(cursor.seek_range_k::<[u8],[u8]>(self, key).to_opt()) {
+            Some((k, _)) if dupsort &&
+                Ordering::Equal == cursor::cmp_keys(cursor, k, key) =>
+                Ok(try!(cursor.next_nodup::<[u8],[u8]>(self).to_opt())
+                   .is_some()),
+            found => Ok(found.is_some()),
+        }
+    }
+
+    /// Deletes items starting at the current position of `cursor` until
+    /// `in_range` rejects a key/value pair or the end of the database is
+    /// reached. On `DUPSORT` databases, whole keys are deleted if
+    /// `whole_keys` is true.
+    fn del_while<F : Fn (&Cursor, &[u8], &[u8]) -> bool>
+        (&mut self, mut cursor: Cursor, mut found: bool, whole_keys: bool,
+         in_range: F) -> Result<usize>
+    {
+        let dupsort = cursor::db_flags(&cursor).contains(db::DUPSORT);
+        let mut deleted = 0;
+        while found {
+            {
+                let (key, val) = try!(cursor.get_current::<[u8],[u8]>(self));
+                if !in_range(&cursor, key, val) {
+                    break;
+                }
+            }
+
+            // After a deletion, the cursor is left such that the next
+            // `MDB_NEXT` or `MDB_NEXT_NODUP` produces the item which
+            // followed the deleted one.
+            if dupsort && whole_keys {
+                deleted += try!(cursor.count());
+                try!(cursor.del(self, del::NODUPDATA));
+                found = try!(cursor.next_nodup::<[u8],[u8]>(self).to_opt())
+                    .is_some();
+            } else {
+                deleted += 1;
+                try!(cursor.del(self, del::Flags::empty()));
+                found = try!(cursor.next::<[u8],[u8]>(self).to_opt())
+                    .is_some();
+            }
+        }
+        Ok(deleted)
+    }
+
     /// Completely clears the content of the given database.
     ///
     /// ## Example
@@ -0,0 +1,405 @@
+// Copyright 2016 FullContact, Inc
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use std::marker::PhantomData;
+use std::ops::{Deref, DerefMut};
+
+use dbi::{Database, DatabaseOptions};
+use env::EnvRef;
+use error::Result;
+use cursor::Cursor;
+use tx::{put, del, ConstTransaction, ConstAccessor, WriteAccessor};
+use traits::*;
+
+/// A `Database` handle which carries the types of its keys and values.
+///
+/// A `TypedDatabase<K, V>` is a thin wrapper around a `Database` whose
+/// `get`, `put` and `cursor` methods are fixed to key type `K` and value type
+/// `V`, so that neither needs to be specified (or inferred from context) at
+/// each call site, and a key or value of the wrong type is a compile-time
+/// error rather than a `Mismatch` or `ValRejected` at run-time.
+///
+/// The types are purely a property of the handle. LMDB itself has no notion
+/// of them, so nothing prevents another handle (typed or not) to the same
+/// database from using different types.
+///
+/// A `TypedDatabase` is normally opened with `TypedDatabaseOptions::open()`,
+/// from the options returned by `DatabaseOptions::create_map()` and
+/// friends. The underlying `Database` is available through `untyped()`, for
+/// the rare cases where the types need to be bypassed.
+///
+/// ## Lifetime
+///
+/// The lifetime parameter is that of the underlying `Database`; see the
+/// documentation there.
+///
+/// ## Example
+///
+/// ```
+/// # include!("src/example_helpers.rs");
+/// # fn main() {
+/// # let env = create_env();
+/// use lmdb::Unaligned as U;
+///
+/// let db = lmdb::DatabaseOptions::create_map::<U<u32>, str>()
+///   .open(&env, Some("example")).unwrap();
+/// let txn = lmdb::WriteTransaction::new(&env).unwrap();
+/// {
+///   let mut access = txn.access();
+///   let f = lmdb::put::Flags::empty();
+///   db.put(&mut access, &U::new(2), "two", f).unwrap();
+///   db.put(&mut access, &U::new(1), "one", f).unwrap();
+///   // Does not compile: the database only holds `str` values.
+///   // db.put(&mut access, &U::new(3), &U::new(3u32), f).unwrap();
+///
+///   assert_eq!("two", db.get(&access, &U::new(2)).unwrap());
+///
+///   let mut cursor = db.cursor(&txn).unwrap();
+///   let (k, v) = cursor.first(&access).unwrap();
+///   assert_eq!((1, "one"), (k.get(), v));
+/// }
+/// txn.commit().unwrap();
+/// # }
+/// ```
+///
+/// A `TypedDatabase` cannot be used where an untyped `&Database` is
+/// expected without going through `untyped()`, so this does not compile:
+///
+/// ```compile_fail
+/// # include!("src/example_helpers.rs");
+/// # fn main() {
+/// # let env = create_env();
+/// use lmdb::Unaligned as U;
+///
+/// let db = lmdb::DatabaseOptions::create_map::<str, U<u64>>()
+///   .open(&env, Some("example")).unwrap();
+/// let txn = lmdb::ReadTransaction::new(&env).unwrap();
+/// let access = txn.access();
+/// access.get::<str, str>(&*db, "key").unwrap();
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct TypedDatabase<'a, K : ?Sized, V : ?Sized> {
+    db: Database<'a>,
+    _types: PhantomData<fn (&K, &V)>,
+}
+
+impl<'a, K : ?Sized, V : ?Sized> TypedDatabase<'a, K, V> {
+    /// Wraps an existing `Database` handle, asserting that its keys are of
+    /// type `K` and its values of type `V`.
+    ///
+    /// Nothing is checked about the current contents of the database; values
+    /// which do not convert to the asserted types will cause errors when they
+    /// are read.
+    pub fn new(db: Database<'a>) -> Self {
+        TypedDatabase {
+            db: db,
+            _types: PhantomData,
+        }
+    }
+
+    /// Opens a database with the given options, as with `Database::open`,
+    /// and types the resulting handle.
+    pub fn open<E : Into<EnvRef<'a>>>(env: E, name: Option<&str>,
+                                      options: &DatabaseOptions)
+                                      -> Result<Self> {
+        Database::open(env, name, options).map(TypedDatabase::new)
+    }
+
+    /// Unwraps this handle into the untyped `Database`.
+    pub fn into_database(self) -> Database<'a> {
+        self.db
+    }
+
+    /// Returns the untyped `Database` underlying this handle.
+    ///
+    /// Nothing checks the types of keys and values accessed through the
+    /// result.
+    pub fn untyped(&self) -> &Database<'a> {
+        &self.db
+    }
+
+    /// Looks `key` up in this database.
+    ///
+    /// This is `ConstAccessor::get()` with the key and value types fixed.
+    #[inline]
+    pub fn get<'access>(&self, access: &'access ConstAccessor, key: &K)
+                        -> Result<&'access V>
+    where K : AsLmdbBytes, V : FromLmdbBytes {
+        access.get(&self.db, key)
+    }
+
+    /// Stores `key` and `val` into this database.
+    ///
+    /// This is `WriteAccessor::put()` with the key and value types fixed.
+    #[inline]
+    pub fn put(&self, access: &mut WriteAccessor, key: &K, val: &V,
+               flags: put::Flags) -> Result<()>
+    where K : AsLmdbBytes, V : AsLmdbBytes {
+        access.put(&self.db, key, val, flags)
+    }
+
+    /// Deletes all items with the given key from this database.
+    ///
+    /// This is `WriteAccessor::del_key()` with the key type fixed.
+    #[inline]
+    pub fn del_key(&self, access: &mut WriteAccessor, key: &K) -> Result<()>
+    where K : AsLmdbBytes {
+        access.del_key(&self.db, key)
+    }
+
+    /// Deletes the given key/value pair from this database.
+    ///
+    /// This is `WriteAccessor::del_item()` with the key and value types
+    /// fixed.
+    #[inline]
+    pub fn del_item(&self, access: &mut WriteAccessor, key: &K, val: &V)
+                    -> Result<()>
+    where K : AsLmdbBytes, V : AsLmdbBytes {
+        access.del_item(&self.db, key, val)
+    }
+
+    /// Opens a cursor into this database within the given transaction.
+    ///
+    /// The returned cursor has the same key and value types as this handle.
+    #[inline]
+    pub fn cursor<'txn, 'db>(&'db self, txn: &'txn ConstTransaction)
+                             -> Result<TypedCursor<'txn, 'db, K, V>> {
+        txn.cursor(&self.db).map(TypedCursor::new)
+    }
+}
+
+impl<'a, K : ?Sized, V : ?Sized> From<Database<'a>>
+for TypedDatabase<'a, K, V> {
+    fn from(db: Database<'a>) -> Self {
+        TypedDatabase::new(db)
+    }
+}
+
+/// Options for opening a database, along with the types of its keys and
+/// values.
+///
+/// This is returned by `DatabaseOptions::create_map()`,
+/// `create_multimap()` and `create_multimap_unsized()`, and dereferences to
+/// the underlying `DatabaseOptions` so that the options can be adjusted
+/// further.
+#[derive(Debug)]
+pub struct TypedDatabaseOptions<K : ?Sized, V : ?Sized> {
+    options: DatabaseOptions,
+    _types: PhantomData<fn (&K, &V)>,
+}
+
+impl<K : ?Sized, V : ?Sized> TypedDatabaseOptions<K, V> {
+    /// Wraps `options`, asserting that databases opened with them have keys
+    /// of type `K` and values of type `V`.
+    pub fn new(options: DatabaseOptions) -> Self {
+        TypedDatabaseOptions {
+            options: options,
+            _types: PhantomData,
+        }
+    }
+
+    /// Opens a database with these options, as with `Database::open()`,
+    /// returning a handle typed with `K` and `V`.
+    pub fn open<'a, E : Into<EnvRef<'a>>>(&self, env: E, name: Option<&str>)
+                                          -> Result<TypedDatabase<'a, K, V>> {
+        TypedDatabase::open(env, name, &self.options)
+    }
+
+    /// Unwraps these options into the untyped `DatabaseOptions`.
+    pub fn into_options(self) -> DatabaseOptions {
+        self.options
+    }
+}
+
+impl<K : ?Sized, V : ?Sized> Clone for TypedDatabaseOptions<K, V> {
+    fn clone(&self) -> Self {
+        TypedDatabaseOptions::new(self.options.clone())
+    }
+}
+
+impl<K : ?Sized, V : ?Sized> Deref for TypedDatabaseOptions<K, V> {
+    type Target = DatabaseOptions;
+
+    fn deref(&self) -> &DatabaseOptions {
+        &self.options
+    }
+}
+
+impl<K : ?Sized, V : ?Sized> DerefMut for TypedDatabaseOptions<K, V> {
+    fn deref_mut(&mut self) -> &mut DatabaseOptions {
+        &mut self.options
+    }
+}
+
+/// A `Cursor` whose key and value types are fixed.
+///
+/// This is normally obtained from `TypedDatabase::cursor()`. Each method
+/// corresponds to the method of the same name on `Cursor`, but with `K` and
+/// `V` in place of the free type parameters.
+///
+/// ## Lifetime
+///
+/// The lifetimes are the same as those of `Cursor`.
+#[derive(Debug)]
+pub struct TypedCursor<'txn, 'db, K : ?Sized, V : ?Sized> {
+    cursor: Cursor<'txn, 'db>,
+    _types: PhantomData<fn (&K, &V)>,
+}
+
+macro_rules! typed_cursor_0_kv {
+    ($($(#[$doc:meta])* fn $method:ident;)*) => { $(
+        $(#[$doc])*
+        #[inline]
+        pub fn $method<'access>(&mut self, access: &'access ConstAccessor)
+                                -> Result<(&'access K, &'access V)>
+        where K : FromLmdbBytes, V : FromLmdbBytes {
+            self.cursor.$method(access)
+        }
+    )* }
+}
+
+macro_rules! typed_cursor_0_v {
+    ($($(#[$doc:meta])* fn $method:ident;)*) => { $(
+        $(#[$doc])*
+        #[inline]
+        pub fn $method<'access>(&mut self, access: &'access ConstAccessor)
+                                -> Result<&'access V>
+        where V : FromLmdbBytes {
+            self.cursor.$method(access)
+        }
+    )* }
+}
+
+impl<'txn, 'db, K : ?Sized, V : ?Sized> TypedCursor<'txn, 'db, K, V> {
+    /// Wraps an existing `Cursor`, asserting that the keys and values it
+    /// reads and writes are of types `K` and `V`.
+    pub fn new(cursor: Cursor<'txn, 'db>) -> Self {
+        TypedCursor {
+            cursor: cursor,
+            _types: PhantomData,
+        }
+    }
+
+    /// Unwraps this cursor into the untyped `Cursor`.
+    pub fn into_cursor(self) -> Cursor<'txn, 'db> {
+        self.cursor
+    }
+
+    /// Returns the untyped `Cursor` underlying this one.
+    pub fn as_cursor(&mut self) -> &mut Cursor<'txn, 'db> {
+        &mut self.cursor
+    }
+
+    typed_cursor_0_kv! {
+        /// See `Cursor::first()`.
+        fn first;
+        /// See `Cursor::get_current()`.
+        fn get_current;
+        /// See `Cursor::last()`.
+        fn last;
+        /// See `Cursor::next()`.
+        fn next;
+        /// See `Cursor::next_dup()`.
+        fn next_dup;
+        /// See `Cursor::next_nodup()`.
+        fn next_nodup;
+        /// See `Cursor::prev()`.
+        fn prev;
+        /// See `Cursor::prev_dup()`.
+        fn prev_dup;
+        /// See `Cursor::prev_nodup()`.
+        fn prev_nodup;
+    }
+
+    typed_cursor_0_v! {
+        /// See `Cursor::first_dup()`.
+        fn first_dup;
+        /// See `Cursor::last_dup()`.
+        fn last_dup;
+    }
+
+    /// See `Cursor::seek_kv()`.
+    #[inline]
+    pub fn seek_kv(&mut self, key: &K, val: &V) -> Result<()>
+    where K : AsLmdbBytes, V : AsLmdbBytes {
+        self.cursor.seek_kv(key, val)
+    }
+
+    /// See `Cursor::seek_k_nearest_v()`.
+    #[inline]
+    pub fn seek_k_nearest_v<'access>(&mut self,
+                                     access: &'access ConstAccessor,
+                                     key: &K, val: &V)
+                                     -> Result<&'access V>
+    where K : AsLmdbBytes, V : AsLmdbBytes + FromLmdbBytes {
+        self.cursor.seek_k_nearest_v(access, key, val)
+    }
+
+    /// See `Cursor::seek_k()`.
+    #[inline]
+    pub fn seek_k<'access>(&mut self, access: &'access ConstAccessor, key: &K)
+                           -> Result<&'access V>
+    where K : AsLmdbBytes, V : FromLmdbBytes {
+        self.cursor.seek_k::<K, V>(access, key)
+    }
+
+    /// See `Cursor::seek_k_both()`.
+    #[inline]
+    pub fn seek_k_both<'access>(&mut self, access: &'access ConstAccessor,
+                                key: &K)
+                                -> Result<(&'access K, &'access V)>
+    where K : AsLmdbBytes + FromLmdbBytes, V : FromLmdbBytes {
+        self.cursor.seek_k_both(access, key)
+    }
+
+    /// See `Cursor::seek_range_k()`.
+    #[inline]
+    pub fn seek_range_k<'access>(&mut self, access: &'access ConstAccessor,
+                                 key: &K)
+                                 -> Result<(&'access K, &'access V)>
+    where K : AsLmdbBytes + FromLmdbBytes, V : FromLmdbBytes {
+        self.cursor.seek_range_k(access, key)
+    }
+
+    /// See `Cursor::put()`.
+    #[inline]
+    pub fn put(&mut self, access: &mut WriteAccessor,
+               key: &K, val: &V, flags: put::Flags) -> Result<()>
+    where K : AsLmdbBytes, V : AsLmdbBytes {
+        self.cursor.put(access, key, val, flags)
+    }
+
+    /// See `Cursor::overwrite()`.
+    #[inline]
+    pub fn overwrite(&mut self, access: &mut WriteAccessor,
+                     key: &K, val: &V, flags: put::Flags) -> Result<()>
+    where K : AsLmdbBytes, V : AsLmdbBytes {
+        self.cursor.overwrite(access, key, val, flags)
+    }
+
+    /// See `Cursor::del()`.
+    #[inline]
+    pub fn del(&mut self, access: &mut WriteAccessor, flags: del::Flags)
+               -> Result<()> {
+        self.cursor.del(access, flags)
+    }
+
+    /// See `Cursor::count()`.
+    #[inline]
+    pub fn count(&mut self) -> Result<usize> {
+        self.cursor.count()
+    }
+}
+
+impl<'txn, 'db, K : ?Sized, V : ?Sized> From<Cursor<'txn, 'db>>
+for TypedCursor<'txn, 'db, K, V> {
+    fn from(cursor: Cursor<'txn, 'db>) -> Self {
+        TypedCursor::new(cursor)
+    }
+}